@@ -0,0 +1,38 @@
+/* C interface of rust-ole, built with the `ffi` feature as a shared library:
+ *   cargo rustc --release --features ffi --crate-type cdylib
+ * a panic inside the library is reported as OLE_ERR_OTHER (NULL from ole_open) */
+#ifndef RUST_OLE_H
+#define RUST_OLE_H
+
+#include <stddef.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define OLE_OK 0
+#define OLE_ERR_NULL_POINTER 1
+#define OLE_ERR_OUT_OF_RANGE 2
+#define OLE_ERR_BUFFER_TOO_SMALL 3
+#define OLE_ERR_IO 4
+#define OLE_ERR_INVALID_FORMAT 5
+#define OLE_ERR_INVALID_ENTRY 6
+#define OLE_ERR_NOT_FOUND 7
+#define OLE_ERR_OTHER 8
+
+typedef struct OleHandle OleHandle;
+
+/* NULL when the file can't be opened or parsed; the streams are listed here */
+OleHandle *ole_open(const char *path);
+int ole_stream_count(const OleHandle *ole, size_t *count);
+/* *len: buffer size in, path length out; the path isn't NUL-terminated */
+int ole_stream_path(const OleHandle *ole, size_t index, unsigned char *out, size_t *len);
+/* *len: buffer size in, stream size out */
+int ole_read_stream(const OleHandle *ole, size_t index, unsigned char *out, size_t *len);
+void ole_free(OleHandle *ole);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,92 @@
+use crate::directory::{clsid, ObjectType, OLE_SUFFIX};
+use crate::ole::Ole;
+
+/// what kind of document a compound file most likely holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DocumentKind {
+    /// Word 97-2003 `.doc`
+    Word,
+    /// Excel `.xls`
+    Excel,
+    /// PowerPoint 97-2003 `.ppt`
+    PowerPoint,
+    /// Windows Installer package, patch or transform
+    Msi,
+    /// Outlook `.msg`
+    OutlookMessage,
+    Unknown,
+}
+
+const ROOT_CLSIDS: [([u8; 16], DocumentKind); 8] = [
+    (clsid(0x00020906, 0, 0, OLE_SUFFIX), DocumentKind::Word),
+    (clsid(0x00020900, 0, 0, OLE_SUFFIX), DocumentKind::Word),
+    (clsid(0x00020820, 0, 0, OLE_SUFFIX), DocumentKind::Excel),
+    (clsid(0x00020810, 0, 0, OLE_SUFFIX), DocumentKind::Excel),
+    (clsid(0x64818D10, 0x4F9B, 0x11CF, [0x86, 0xEA, 0x00, 0xAA, 0x00, 0xB9, 0x29, 0xE8]), DocumentKind::PowerPoint),
+    (clsid(0x000C1084, 0, 0, OLE_SUFFIX), DocumentKind::Msi),
+    (clsid(0x000C1086, 0, 0, OLE_SUFFIX), DocumentKind::Msi),
+    (clsid(0x000C1082, 0, 0, OLE_SUFFIX), DocumentKind::Msi),
+];
+
+/// streams at the root that give the application away
+const SIGNATURE_STREAMS: [(&str, DocumentKind); 6] = [
+    ("WordDocument", DocumentKind::Word),
+    ("Workbook", DocumentKind::Excel),
+    ("Book", DocumentKind::Excel),
+    ("PowerPoint Document", DocumentKind::PowerPoint),
+    ("__properties_version1.0", DocumentKind::OutlookMessage),
+    ("__nameid_version1.0", DocumentKind::OutlookMessage),
+];
+
+impl Ole {
+    /// guess the document type from the root CLSID, falling back to well-known
+    /// stream names at the root when the CLSID is blank or unknown
+    pub fn classify(&self) -> DocumentKind {
+        let Some(root) = self.entries_ref().ok().and_then(|v| v.first()) else {
+            return DocumentKind::Unknown;
+        };
+        if let Some((_, kind)) = ROOT_CLSIDS.iter().find(|(id, _)| *id == root.clsid) {
+            return *kind;
+        }
+        for (name, kind) in SIGNATURE_STREAMS {
+            if let Ok(Some(idx)) = self.find_child(0, name) {
+                if self.entries_ref().is_ok_and(|v| v[idx].object_type == ObjectType::Stream) {
+                    return kind;
+                }
+            }
+        }
+        DocumentKind::Unknown
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DocumentKind;
+    use crate::test_util::*;
+
+    #[test]
+    fn classify_by_stream_names() {
+        let doc = open(&build(vec![
+            stream("WordDocument", pattern(6144, 7)),
+            stream("1Table", pattern(4608, 3)),
+        ]));
+        assert_eq!(doc.classify(), DocumentKind::Word);
+
+        let xls = open(&build(vec![stream("Workbook", pattern(5000, 1))]));
+        assert_eq!(xls.classify(), DocumentKind::Excel);
+
+        let other = open(&build(vec![stream("Contents", pattern(100, 1))]));
+        assert_eq!(other.classify(), DocumentKind::Unknown);
+    }
+
+    #[test]
+    fn classify_by_root_clsid() {
+        let mut bytes = build(vec![stream("Contents", pattern(100, 1))]);
+        // the root is the first entry of the directory in sector 1, its CLSID is at 0x50
+        let offset = sector_offset(1) + 0x50;
+        bytes[offset..offset + 16].copy_from_slice(&[
+            0x20, 0x08, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0xC0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x46,
+        ]);
+        assert_eq!(open(&bytes).classify(), DocumentKind::Excel);
+    }
+}
@@ -1,5 +1,5 @@
 use std::fmt::{Display, Formatter};
-use binrw::{binrw, BinRead, BinWrite};
+use binrw::binrw;
 use thiserror::Error;
 
 pub const MAX_REG_SECT: u32 = 0xFFFFFFFA;
@@ -25,6 +25,7 @@ pub const FREE_SECT: u32 = 0xFFFFFFFF;
 #[binrw]
 #[brw(little)]
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum SectorType {
     #[brw(magic(0xFFFFFFFAu32))]
     MaxRegSect,
@@ -42,20 +43,58 @@ pub enum SectorType {
     RegularSect(u32),
 }
 
+impl SectorType {
+    /// the name MS-CFB gives the value, `REGSECT` for any regular sector number
+    pub fn as_spec_name(&self) -> &'static str {
+        match self {
+            SectorType::MaxRegSect => "MAXREGSECT",
+            SectorType::NotApplicable => "NOTAPPLICABLE",
+            SectorType::DifSect => "DIFSECT",
+            SectorType::FatSect => "FATSECT",
+            SectorType::EndOfChain => "ENDOFCHAIN",
+            SectorType::FreeSect => "FREESECT",
+            SectorType::RegularSect(_) => "REGSECT",
+        }
+    }
+}
+
+/// reserved values by their spec name, regular sectors by number
 impl Display for SectorType {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
-            SectorType::MaxRegSect => write!(f, "MaxRegSect"),
-            SectorType::NotApplicable => write!(f, "NotApplicable"),
-            SectorType::DifSect => write!(f, "DifatSect"),
-            SectorType::FatSect => write!(f, "FatSect"),
-            SectorType::EndOfChain => write!(f, "EndOfChain"),
-            SectorType::FreeSect => write!(f, "FreeSect"),
-            SectorType::RegularSect(v) => write!(f, "{}", v)
+            SectorType::RegularSect(v) => write!(f, "{}", v),
+            _ => f.write_str(self.as_spec_name()),
         }
     }
 }
 
+impl From<u32> for SectorType {
+    fn from(value: u32) -> Self {
+        match value {
+            MAX_REG_SECT => SectorType::MaxRegSect,
+            NOT_APPLICABLE => SectorType::NotApplicable,
+            DIF_SECT => SectorType::DifSect,
+            FAT_SECT => SectorType::FatSect,
+            END_OF_CHAIN => SectorType::EndOfChain,
+            FREE_SECT => SectorType::FreeSect,
+            v => SectorType::RegularSect(v),
+        }
+    }
+}
+
+impl From<SectorType> for u32 {
+    fn from(value: SectorType) -> Self {
+        match value {
+            SectorType::MaxRegSect => MAX_REG_SECT,
+            SectorType::NotApplicable => NOT_APPLICABLE,
+            SectorType::DifSect => DIF_SECT,
+            SectorType::FatSect => FAT_SECT,
+            SectorType::EndOfChain => END_OF_CHAIN,
+            SectorType::FreeSect => FREE_SECT,
+            SectorType::RegularSect(v) => v,
+        }
+    }
+}
 
 #[binrw]
 #[brw(little)]
@@ -68,6 +107,7 @@ pub enum MinorVersion {
 #[binrw]
 #[brw(little)]
 #[derive(Debug, Eq, PartialEq, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum MajorVersion {
     #[brw(magic(0x0003u16))]
     #[default]
@@ -102,16 +142,59 @@ pub enum OleError {
     InvalidEntrySize,
     #[error("Invalid Entry Chain")]
     InvalidEntryChain,
+    #[error("Invalid Entry Name: {0}")]
+    InvalidEntryName(String),
+    #[error("Path Not Found: {0}")]
+    PathNotFound(String),
+    #[error("Not A Stream: {0}")]
+    NotAStream(String),
+    #[error("Not A Storage: {0}")]
+    NotAStorage(String),
+    #[error("Entry Exists: {0}")]
+    EntryExists(String),
+    #[error("Nesting Too Deep: {0}")]
+    NestingTooDeep(String),
+    #[error("Not Parsed")]
+    NotParsed,
+    #[error("Invalid Root Entry")]
+    InvalidRootEntry,
+    #[error("No Chain: {path}")]
+    NoChain { path: String },
+    #[error("Fat Count Mismatch: header says {expected}, DIFAT lists {found}")]
+    FatCountMismatch { expected: usize, found: usize },
+    #[error("Difat Count Mismatch: header says {expected}, chain has {found}")]
+    DifatCountMismatch { expected: usize, found: usize },
+    #[error("Stream Too Large: {size} bytes, limit {limit}")]
+    StreamTooLarge { size: u64, limit: usize },
+    #[error("Truncated Stream: expected {expected} bytes, file holds {found}")]
+    TruncatedStream { expected: u64, found: u64 },
 }
 
 pub type OleResult<T> = Result<T, OleError>;
+
+/// problems tolerated while parsing, see `Ole::parse_partial`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseWarning {
+    /// the mini FAT couldn't be read, streams below the cutoff are unreadable
+    MiniFatUnreadable(String),
+    /// the header CLSID isn't CLSID_NULL
+    NonZeroHeaderClsid,
+    /// the reserved header bytes aren't zero
+    NonZeroReserved,
+    /// the chain of the named entry loops or leaves the FAT, it was left without one
+    BrokenChain(String, String),
+    /// a version 3 header declares directory sectors, the count is ignored
+    NonZeroDirectorySectorCount(u32),
+    /// a directory sector didn't hold valid entries, its slots were left empty
+    DirectorySectorUnreadable(u32, String),
+}
 pub fn get_valid_entries(entries: &Vec<SectorType>) -> Vec<SectorType> {
     let count = entries.len();
     let mut result = Vec::with_capacity(count);
     for entry in entries {
         if entry == &SectorType::EndOfChain {
             break;
-        } else if let SectorType::RegularSect(v) = entry {
+        } else if let SectorType::RegularSect(_) = entry {
             result.push(entry.clone())
         }
     }
@@ -119,6 +202,30 @@ pub fn get_valid_entries(entries: &Vec<SectorType>) -> Vec<SectorType> {
 }
 
 
+#[deprecated(note = "use Header::sector_size, which follows the header's sector_shift")]
 pub fn get_sector_size(version: &MajorVersion) -> usize {
     if version == &MajorVersion::Version3 { 512 } else { 4096 }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SectorType;
+
+    #[test]
+    fn sector_type_spec_names() {
+        let names = [
+            (SectorType::MaxRegSect, "MAXREGSECT"),
+            (SectorType::NotApplicable, "NOTAPPLICABLE"),
+            (SectorType::DifSect, "DIFSECT"),
+            (SectorType::FatSect, "FATSECT"),
+            (SectorType::EndOfChain, "ENDOFCHAIN"),
+            (SectorType::FreeSect, "FREESECT"),
+        ];
+        for (sector, name) in names {
+            assert_eq!(sector.as_spec_name(), name);
+            assert_eq!(sector.to_string(), name);
+        }
+        assert_eq!(SectorType::RegularSect(42).as_spec_name(), "REGSECT");
+        assert_eq!(SectorType::RegularSect(42).to_string(), "42");
+    }
+}
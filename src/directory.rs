@@ -1,13 +1,90 @@
-use crate::common::{OleResult, SectorType};
+use crate::common::{MajorVersion, SectorType};
 use binrw::{binrw, BinRead, BinWrite};
+use std::borrow::Cow;
+use std::cmp::Ordering;
 use std::fmt::{Display, Formatter};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-/// directory sector
-/// https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-cfb/a94d7445-c4be-49cd-b6b9-2f4abc663817
+pub const MAX_REG_SID: u32 = 0xFFFFFFFA;
+pub const NO_STREAM: u32 = 0xFFFFFFFF;
+/// characters the spec forbids in entry names
+pub const ILLEGAL_NAME_CHARS: [char; 4] = ['/', '\\', ':', '!'];
+
+/// a CLSID as laid out on disk: the first three fields little-endian
+pub(crate) const fn clsid(d1: u32, d2: u16, d3: u16, d4: [u8; 8]) -> [u8; 16] {
+    let a = d1.to_le_bytes();
+    let b = d2.to_le_bytes();
+    let c = d3.to_le_bytes();
+    [a[0], a[1], a[2], a[3], b[0], b[1], c[0], c[1], d4[0], d4[1], d4[2], d4[3], d4[4], d4[5], d4[6], d4[7]]
+}
+
+/// parse a GUID string like `{00020906-0000-0000-C000-000000000046}`, braces optional,
+/// into the on-disk CLSID byte layout
+pub fn clsid_from_str(s: &str) -> Option<[u8; 16]> {
+    let s = s.strip_prefix('{').and_then(|v| v.strip_suffix('}')).unwrap_or(s);
+    let parts = s.split('-').collect::<Vec<&str>>();
+    let [d1, d2, d3, d4, d5] = parts[..] else {
+        return None;
+    };
+    if [d1.len(), d2.len(), d3.len(), d4.len(), d5.len()] != [8, 4, 4, 4, 12] {
+        return None;
+    }
+    let tail = u64::from_str_radix(&format!("{}{}", d4, d5), 16).ok()?;
+    Some(clsid(
+        u32::from_str_radix(d1, 16).ok()?,
+        u16::from_str_radix(d2, 16).ok()?,
+        u16::from_str_radix(d3, 16).ok()?,
+        tail.to_be_bytes(),
+    ))
+}
+
+/// the registry form of an on-disk CLSID, `{00020906-0000-0000-C000-000000000046}`,
+/// which `clsid_from_str` reads back
+pub fn clsid_to_string(clsid: &[u8; 16]) -> String {
+    let d1 = u32::from_le_bytes([clsid[0], clsid[1], clsid[2], clsid[3]]);
+    let d2 = u16::from_le_bytes([clsid[4], clsid[5]]);
+    let d3 = u16::from_le_bytes([clsid[6], clsid[7]]);
+    let tail = clsid[8..].iter().map(|v| format!("{:02X}", v)).collect::<String>();
+    format!("{{{:08X}-{:04X}-{:04X}-{}-{}}}", d1, d2, d3, &tail[..4], &tail[4..])
+}
+
+/// UTF-16LE name bytes up to the first NUL, unpaired surrogates replaced
+pub(crate) fn decode_name(raw: &[u8]) -> Cow<'_, str> {
+    if raw.is_empty() {
+        return Cow::Borrowed("");
+    }
+    let units = raw.chunks(2)
+        .map(|v| match v {
+            [lo, hi] => u16::from_le_bytes([*lo, *hi]),
+            _ => 0xFFFD,
+        })
+        .take_while(|v| *v != 0);
+    Cow::Owned(char::decode_utf16(units)
+        .map(|v| v.unwrap_or(char::REPLACEMENT_CHARACTER))
+        .collect::<String>())
+}
+
+/// tail shared by the CLSIDs of classic OLE servers, `{xxxxxxxx-0000-0000-C000-000000000046}`
+pub(crate) const OLE_SUFFIX: [u8; 8] = [0xC0, 0, 0, 0, 0, 0, 0, 0x46];
 
-const MAX_REG_SID: u32 = 0xFFFFFFFA;
-const NO_STREAM: u32 = 0xFFFFFFFF;
+/// well-known storage CLSIDs and what created them
+const CLASS_NAMES: [([u8; 16], &str); 12] = [
+    (clsid(0x00020906, 0, 0, OLE_SUFFIX), "Microsoft Word 97-2003 Document"),
+    (clsid(0x00020900, 0, 0, OLE_SUFFIX), "Microsoft Word 6.0-7.0 Document"),
+    (clsid(0x00020820, 0, 0, OLE_SUFFIX), "Microsoft Excel 97-2003 Worksheet"),
+    (clsid(0x00020810, 0, 0, OLE_SUFFIX), "Microsoft Excel 5.0/95 Worksheet"),
+    (clsid(0x00020821, 0, 0, OLE_SUFFIX), "Microsoft Excel Chart"),
+    (clsid(0x64818D10, 0x4F9B, 0x11CF, [0x86, 0xEA, 0x00, 0xAA, 0x00, 0xB9, 0x29, 0xE8]), "Microsoft PowerPoint 97-2003 Presentation"),
+    (clsid(0x0002CE02, 0, 0, OLE_SUFFIX), "Microsoft Equation 3.0"),
+    (clsid(0x0003000C, 0, 0, OLE_SUFFIX), "Package"),
+    (clsid(0x00022602, 0, 0, OLE_SUFFIX), "Media Clip"),
+    (clsid(0x000C1084, 0, 0, OLE_SUFFIX), "Windows Installer Package"),
+    (clsid(0x000C1086, 0, 0, OLE_SUFFIX), "Windows Installer Patch"),
+    (clsid(0x000C1082, 0, 0, OLE_SUFFIX), "Windows Installer Transform"),
+];
 
+/// directory sector
+/// https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-cfb/a94d7445-c4be-49cd-b6b9-2f4abc663817
 #[derive(Debug, Clone, BinRead, BinWrite)]
 #[brw(little)]
 #[brw(import(entry_count: u16))]
@@ -18,8 +95,10 @@ pub struct Directory {
 
 #[derive(Debug, Clone, BinRead, BinWrite)]
 #[brw(little)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Entry {
     // Directory Entry Name (64 bytes): This field MUST contain a Unicode string for the storage or stream name encoded in UTF-16. The name MUST be terminated with a UTF-16 terminating null character. Thus, storage and stream names are limited to 32 UTF-16 code points, including the terminating null character. When locating an object in the compound file except for the root storage, the directory entry name is compared by using a special case-insensitive uppercase mapping, described in Red-Black Tree. The following characters are illegal and MUST NOT be part of the name: '/', '\', ':', '!'.
+    #[cfg_attr(feature = "serde", serde(serialize_with = "crate::metadata::name"))]
     pub name: [u8; 64],
     // Directory Entry Name Length (2 bytes): This field MUST match the length of the Directory Entry Name Unicode string in bytes. The length MUST be a multiple of 2 and include the terminating null character in the count. This length MUST NOT exceed 64, the maximum size of the Directory Entry Name field.
     pub name_length: u16,
@@ -34,12 +113,15 @@ pub struct Entry {
     //Child ID (4 bytes): This field contains the stream ID of a child object. If there is no child object, including all entries for stream objects, the field MUST be set to NOSTREAM (0xFFFFFFFF).
     pub child_id: SectorType,
     //CLSID (16 bytes): This field contains an object class GUID, if this entry is for a storage object or root storage object. For a stream object, this field MUST be set to all zeroes. A value containing all zeroes in a storage or root storage directory entry is valid, and indicates that no object class is associated with the storage. If an implementation of the file format enables applications to create storage objects without explicitly setting an object class GUID, it MUST write all zeroes by default. If this value is not all zeroes, the object class GUID can be used as a parameter to start applications.
+    #[cfg_attr(feature = "serde", serde(serialize_with = "crate::metadata::clsid"))]
     pub clsid: [u8; 16],
     //State Bits (4 bytes): This field contains the user-defined flags if this entry is for a storage object or root storage object. For a stream object, this field SHOULD be set to all zeroes because many implementations provide no way for applications to retrieve state bits from a stream object. If an implementation of the file format enables applications to create storage objects without explicitly setting state bits, it MUST write all zeroes by default.
     pub state_bits: u32,
     //Creation Time (8 bytes): This field contains the creation time for a storage object, or all zeroes to indicate that the creation time of the storage object was not recorded. The Windows FILETIME structure is used to represent this field in UTC. For a stream object, this field MUST be all zeroes. For a root storage object, this field MUST be all zeroes, and the creation time is retrieved or set on the compound file itself.
+    #[cfg_attr(feature = "serde", serde(serialize_with = "crate::metadata::filetime"))]
     pub creation_time: u64,
     //Modified Time (8 bytes): This field contains the modification time for a storage object, or all zeroes to indicate that the modified time of the storage object was not recorded. The Windows FILETIME structure is used to represent this field in UTC. For a stream object, this field MUST be all zeroes. For a root storage object, this field MAY<2> be set to all zeroes, and the modified time is retrieved or set on the compound file itself.
+    #[cfg_attr(feature = "serde", serde(serialize_with = "crate::metadata::filetime"))]
     pub modified_time: u64,
     //Starting Sector Location (4 bytes): This field contains the first sector location if this is a stream object. For a root storage object, this field MUST contain the first sector of the mini stream, if the mini stream exists. For a storage object, this field MUST be set to all zeroes.
     pub starting_sector_location: SectorType,
@@ -48,12 +130,13 @@ pub struct Entry {
     pub stream_size: u64,
 
     #[brw(ignore)]
+    #[cfg_attr(feature = "serde", serde(serialize_with = "crate::metadata::chain"))]
     pub chain: Option<Vec<SectorType>>,
 }
 
 impl Display for Entry {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "name: {},\ttype: {},\tcolor: {},\tsize: {}\n", self.name(), self.object_type, self.color, self.stream_size)?;
+        writeln!(f, "name: {},\ttype: {},\tcolor: {},\tsize: {}", self.name(), self.object_type, self.color, self.stream_size)?;
         write!(f, "left sibling: {},\tright_sibling: {},\t", self.left_sibling_id, self.right_sibling_id)?;
         match &self.chain {
             Some(c) => write!(f, "chain: {:?}", c.iter().map(|v| v.to_string()).collect::<Vec<String>>()),
@@ -63,30 +146,165 @@ impl Display for Entry {
 }
 
 impl Entry {
-    pub fn name(&self) -> String {
-        self.name.iter().enumerate().filter_map(|(i, v): (usize, &u8)| {
-            if v != &0 && i % 2 == 0 {
-                Some(*v as char)
-            } else {
-                None
-            }
-        }).collect::<String>()
+    /// create a fresh entry with the given name, siblings and child set to NOSTREAM
+    pub fn new(name: &str, object_type: ObjectType) -> Self {
+        let mut raw = [0u8; 64];
+        let units = name.encode_utf16().take(31).collect::<Vec<u16>>();
+        for (i, unit) in units.iter().enumerate() {
+            raw[i * 2..i * 2 + 2].copy_from_slice(&unit.to_le_bytes());
+        }
+        let starting_sector_location = match object_type {
+            ObjectType::Stream | ObjectType::RootStorage => SectorType::EndOfChain,
+            _ => SectorType::RegularSect(0),
+        };
+        Self {
+            name: raw,
+            name_length: (units.len() as u16 + 1) * 2,
+            object_type,
+            color: Color::Black,
+            left_sibling_id: SectorType::FreeSect,
+            right_sibling_id: SectorType::FreeSect,
+            child_id: SectorType::FreeSect,
+            clsid: [0; 16],
+            state_bits: 0,
+            creation_time: 0,
+            modified_time: 0,
+            starting_sector_location,
+            stream_size: 0,
+            chain: None,
+        }
+    }
+
+    /// an unallocated directory slot
+    pub fn unused() -> Self {
+        let mut entry = Self::new("", ObjectType::Unknown);
+        entry.name_length = 0;
+        entry.color = Color::Red;
+        entry
+    }
+
+    /// decode the first `name_length` bytes as UTF-16LE up to the terminating null,
+    /// surrogate pairs become a single char and unpaired surrogates are replaced with
+    /// U+FFFD
+    ///
+    /// a `name_length` of 0 is the empty name; an odd one leaves a dangling byte that
+    /// also turns into U+FFFD, and one past the 64-byte array is cut to it
+    pub fn name(&self) -> Cow<'_, str> {
+        let len = std::cmp::min(self.name_length as usize, self.name.len());
+        decode_name(&self.name[..len])
+    }
+
+    /// the stream size as the file's major version defines it: version 3 only has
+    /// 32 bits of it and some writers leave junk in the high word, version 4 uses all 64
+    pub fn effective_size(&self, version: &MajorVersion) -> u64 {
+        match version {
+            MajorVersion::Version3 => self.stream_size & 0xFFFFFFFF,
+            MajorVersion::Version4 => self.stream_size,
+        }
+    }
+
+    /// human-readable name of a well-known storage CLSID, `None` when the CLSID is
+    /// null or not in the built-in table
+    pub fn class_name(&self) -> Option<&'static str> {
+        CLASS_NAMES.iter().find(|(id, _)| *id == self.clsid).map(|(_, name)| *name)
+    }
+
+    /// the CLSID as a GUID, `None` for the null CLSID storages carry when no class
+    /// is associated with them
+    #[cfg(feature = "uuid")]
+    pub fn clsid_uuid(&self) -> Option<uuid::Uuid> {
+        // the first three fields are stored little-endian
+        let uuid = uuid::Uuid::from_bytes_le(self.clsid);
+        (!uuid.is_nil()).then_some(uuid)
+    }
+
+    /// creation time as an ISO-8601 UTC string, `None` when not set
+    pub fn creation_time_iso8601(&self) -> Option<String> {
+        filetime_iso8601(self.creation_time)
+    }
+
+    /// creation time as a `SystemTime`, `None` when not set or not representable
+    pub fn creation_time_systemtime(&self) -> Option<SystemTime> {
+        filetime_systemtime(self.creation_time)
+    }
+
+    /// modified time as a `SystemTime`, `None` when not set or not representable
+    pub fn modified_time_systemtime(&self) -> Option<SystemTime> {
+        filetime_systemtime(self.modified_time)
     }
 
     pub fn parse(&mut self) {}
 
     pub fn append_chain(&mut self, sector: Vec<SectorType>) {
-        if self.chain.is_some() {
-            self.chain.as_mut().unwrap().extend(sector);
-        } else {
-            self.chain = Some(sector);
+        match self.chain.as_mut() {
+            Some(chain) => chain.extend(sector),
+            None => self.chain = Some(sector),
+        }
+    }
+}
+
+/// seconds from the FILETIME epoch 1601-01-01 to the unix epoch
+const FILETIME_UNIX_OFFSET: u64 = 11_644_473_600;
+
+/// a FILETIME as a `SystemTime`, `None` for zero or a time the platform can't hold
+pub(crate) fn filetime_systemtime(filetime: u64) -> Option<SystemTime> {
+    if filetime == 0 {
+        return None;
+    }
+    let since_1601 = Duration::new(filetime / 10_000_000, (filetime % 10_000_000) as u32 * 100);
+    UNIX_EPOCH.checked_sub(Duration::from_secs(FILETIME_UNIX_OFFSET))?.checked_add(since_1601)
+}
+
+/// format a FILETIME (100ns ticks since 1601-01-01 UTC) as `YYYY-MM-DDTHH:MM:SSZ`
+pub(crate) fn filetime_iso8601(filetime: u64) -> Option<String> {
+    if filetime == 0 {
+        return None;
+    }
+    let seconds = (filetime / 10_000_000) as i64;
+    // shift the day count to start at 0000-03-01, 584694 days before 1601-01-01
+    let days = seconds.div_euclid(86400) + 584694;
+    let time = seconds.rem_euclid(86400);
+
+    // days since 0000-03-01 to a civil date, years counted from March
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    Some(format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z", year, month, day, time / 3600, time % 3600 / 60, time % 60))
+}
+
+/// compare two entry names the way the red-black tree orders siblings:
+/// shorter names first, then code unit by code unit after uppercasing
+pub fn compare_names(a: &str, b: &str) -> Ordering {
+    let a = a.encode_utf16().collect::<Vec<u16>>();
+    let b = b.encode_utf16().collect::<Vec<u16>>();
+    a.len().cmp(&b.len()).then_with(|| {
+        a.iter().map(|v| uppercase(*v)).cmp(b.iter().map(|v| uppercase(*v)))
+    })
+}
+
+fn uppercase(unit: u16) -> u16 {
+    match char::from_u32(unit as u32) {
+        Some(c) => {
+            let mut upper = c.to_uppercase();
+            match (upper.next(), upper.next()) {
+                (Some(u), None) if (u as u32) <= 0xFFFF => u as u16,
+                _ => unit,
+            }
         }
+        None => unit,
     }
 }
 
 #[binrw]
 #[brw(little)]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum ObjectType {
     #[brw(magic(0x00u8))]
     Unknown,
@@ -111,7 +329,8 @@ impl Display for ObjectType {
 
 #[binrw]
 #[brw(little)]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum Color {
     #[brw(magic(0x00u8))]
     Red,
@@ -126,4 +345,131 @@ impl Display for Color {
             Color::Black => write!(f, "black")
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{clsid, Entry, ObjectType, OLE_SUFFIX};
+    use crate::common::MajorVersion;
+    use std::time::{Duration, UNIX_EPOCH};
+    use crate::test_util::*;
+
+    #[test]
+    fn name_decodes_surrogate_pairs() {
+        let entry = Entry::new("📄", ObjectType::Storage);
+        assert_eq!(&entry.name[..4], &[0x3D, 0xD8, 0xC4, 0xDC]);
+        assert_eq!(entry.name_length, 6);
+        assert_eq!(entry.name(), "📄");
+
+        let ole = open(&build(vec![storage("Docs 📄", vec![stream("a", vec![1])])]));
+        assert_eq!(ole.entries.as_ref().unwrap()[1].name(), "Docs 📄");
+    }
+
+    #[test]
+    fn name_replaces_unpaired_surrogates() {
+        let mut entry = Entry::new("ab", ObjectType::Stream);
+        // a lone high surrogate between two letters
+        entry.name[..6].copy_from_slice(&[0x61, 0x00, 0x3D, 0xD8, 0x62, 0x00]);
+        assert_eq!(entry.name(), "a\u{FFFD}b");
+    }
+
+    #[test]
+    fn name_decodes_non_latin_names() {
+        let ole = open(&build(vec![
+            stream("Документ", pattern(10, 1)),
+            storage("文書", vec![stream("本文ストリーム", pattern(10, 2))]),
+        ]));
+        let names = ole.entries.as_ref().unwrap().iter().map(|v| v.name().into_owned()).collect::<Vec<String>>();
+        assert!(names.contains(&"Документ".to_string()));
+        assert!(names.contains(&"文書".to_string()));
+        assert_eq!(ole.read_stream("/文書/本文ストリーム").unwrap(), pattern(10, 2));
+    }
+
+    #[test]
+    fn name_honors_name_length() {
+        let mut entry = Entry::new("abc", ObjectType::Stream);
+        entry.name_length = 0;
+        assert_eq!(entry.name(), "");
+        // without the terminating null
+        entry.name_length = 4;
+        assert_eq!(entry.name(), "ab");
+        entry.name_length = 5;
+        assert_eq!(entry.name(), "ab\u{FFFD}");
+        entry.name_length = 200;
+        assert_eq!(entry.name(), "abc");
+    }
+
+    #[test]
+    fn creation_time_iso8601() {
+        let mut entry = Entry::new("WordDocument", ObjectType::Stream);
+        assert_eq!(entry.creation_time_iso8601(), None);
+
+        // unix time 1234567890
+        entry.creation_time = 128790414900000000;
+        assert_eq!(entry.creation_time_iso8601().unwrap(), "2009-02-13T23:31:30Z");
+        entry.creation_time = 1;
+        assert_eq!(entry.creation_time_iso8601().unwrap(), "1601-01-01T00:00:00Z");
+        // leap day
+        entry.creation_time = (951782400 + 11644473600) * 10_000_000;
+        assert_eq!(entry.creation_time_iso8601().unwrap(), "2000-02-29T00:00:00Z");
+    }
+
+    #[test]
+    fn filetime_systemtime() {
+        let mut entry = Entry::new("Storage", ObjectType::Storage);
+        assert_eq!(entry.creation_time_systemtime(), None);
+        assert_eq!(entry.modified_time_systemtime(), None);
+
+        // unix time 1234567890.5
+        entry.creation_time = 128790414905000000;
+        entry.modified_time = 116444736000000000;
+        assert_eq!(entry.creation_time_systemtime(), Some(UNIX_EPOCH + Duration::from_millis(1234567890500)));
+        assert_eq!(entry.modified_time_systemtime(), Some(UNIX_EPOCH));
+
+        // before 1970 and the far end of the range still convert
+        entry.modified_time = 1;
+        assert_eq!(
+            entry.modified_time_systemtime(),
+            Some(UNIX_EPOCH - Duration::from_secs(11_644_473_600) + Duration::from_nanos(100))
+        );
+        entry.modified_time = u64::MAX;
+        assert!(entry.modified_time_systemtime().unwrap() > UNIX_EPOCH);
+    }
+
+    #[test]
+    fn class_name() {
+        let mut entry = Entry::new("Equation Native", ObjectType::Storage);
+        assert_eq!(entry.class_name(), None);
+
+        // {0002CE02-0000-0000-C000-000000000046}
+        entry.clsid = [0x02, 0xCE, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0xC0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x46];
+        assert_eq!(entry.class_name(), Some("Microsoft Equation 3.0"));
+
+        entry.clsid = clsid(0x00020820, 0, 0, OLE_SUFFIX);
+        assert_eq!(entry.class_name(), Some("Microsoft Excel 97-2003 Worksheet"));
+
+        entry.clsid = clsid(0x12345678, 0, 0, OLE_SUFFIX);
+        assert_eq!(entry.class_name(), None);
+    }
+
+    #[test]
+    fn effective_size() {
+        let mut entry = Entry::new("WordDocument", ObjectType::Stream);
+        entry.stream_size = 0x0000_0001_0000_1800;
+        assert_eq!(entry.effective_size(&MajorVersion::Version3), 0x1800);
+        assert_eq!(entry.effective_size(&MajorVersion::Version4), 0x0000_0001_0000_1800);
+    }
+
+    #[cfg(feature = "uuid")]
+    #[test]
+    fn clsid_uuid() {
+        let mut entry = Entry::new("Equation Native", ObjectType::Storage);
+        assert_eq!(entry.clsid_uuid(), None);
+
+        entry.clsid = [0x02, 0xCE, 0x02, 0x00, 0x34, 0x12, 0x78, 0x56, 0xC0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x46];
+        let uuid = entry.clsid_uuid().unwrap();
+        assert_eq!(uuid.as_bytes(), &[0x00, 0x02, 0xCE, 0x02, 0x12, 0x34, 0x56, 0x78, 0xC0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x46]);
+        assert_eq!(format!("{:X}", uuid.braced()), "{0002CE02-1234-5678-C000-000000000046}");
+        assert_eq!(super::clsid_from_str(&uuid.to_string()), Some(entry.clsid));
+    }
+}
@@ -0,0 +1,1455 @@
+use crate::common::{MajorVersion, OleError, OleResult, SectorType};
+use crate::difat::{AllEntryDifat, Difat};
+use crate::directory::{compare_names, Color, Directory, Entry, ObjectType, ILLEGAL_NAME_CHARS};
+use crate::fat::Fat;
+use crate::mini_fat::MiniFat;
+use crate::ole::{Ole, SPEC_MINI_STREAM_CUTOFF};
+use binrw::BinWrite;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{Cursor, ErrorKind, Read, Write};
+
+/// in-place editing of a parsed compound file
+///
+/// edits allocate from the FAT/mini FAT held in memory and the directory and
+/// allocation tables are written back into their sectors in `body` after every
+/// operation, so `body` always reflects the current state of the file
+impl Ole {
+    /// write a stream from a reader of unknown length, replacing the stream at `path`
+    /// or creating it inside its parent storage
+    ///
+    /// at most `SPEC_MINI_STREAM_CUTOFF` bytes are buffered to decide between the mini
+    /// stream and regular sectors, after that sectors are allocated as data arrives;
+    /// the old contents are only released once the reader is drained, a failed read
+    /// frees what was allocated and leaves the stream, or its absence, as it was
+    pub fn write_stream_from_reader(&mut self, path: &str, reader: &mut impl Read) -> OleResult<u64> {
+        self.settle()?;
+        self.free_hint = 0;
+        let (parent, name, existing) = self.find_stream(path)?;
+        let cutoff = self.mini_stream_cutoff() as u64;
+        let lookahead = std::cmp::min(cutoff, SPEC_MINI_STREAM_CUTOFF as u64);
+        let mut pending = vec![];
+        reader.by_ref().take(lookahead).read_to_end(&mut pending)?;
+        let mut mini = (pending.len() as u64) < cutoff;
+
+        let old_mini = match existing {
+            Some(idx) => self.in_mini_stream(&self.entries_ref()?[idx]),
+            None => false,
+        };
+        if mini && !pending.is_empty() {
+            self.move_small_streams_to_mini_stream(existing.unwrap_or(usize::MAX))?;
+        }
+        let mut chain = vec![];
+        let written = self.fill_chain(&mut chain, pending, reader, &mut mini);
+        // the entry of a new stream is only created once its data is in place
+        let slot = written.and_then(|total| match existing {
+            Some(idx) => Ok((idx, total)),
+            None => self.insert_entry(parent, Entry::new(name, ObjectType::Stream)).map(|idx| (idx, total)),
+        });
+        let (idx, total) = match slot {
+            Ok(v) => v,
+            Err(err) => {
+                self.free_chain(&chain, mini)?;
+                self.flush_tables()?;
+                return Err(err);
+            }
+        };
+
+        let entry = &mut self.entries_mut()?[idx];
+        let old = entry.chain.replace(chain.clone()).unwrap_or_default();
+        entry.starting_sector_location = chain.first().cloned().unwrap_or(SectorType::EndOfChain);
+        entry.stream_size = total;
+        self.free_chain(&old, old_mini)?;
+        self.flush_tables()?;
+        Ok(total)
+    }
+
+    /// in a file without a mini FAT small streams sit in the regular FAT, and once a
+    /// mini FAT exists `in_mini_stream` would look for them in the mini stream; so
+    /// before the first mini sector is allocated they are moved there, all but `skip`
+    fn move_small_streams_to_mini_stream(&mut self, skip: usize) -> OleResult<()> {
+        if matches!(self.header.first_mini_fat_sector_location, SectorType::RegularSect(_)) {
+            return Ok(());
+        }
+        let cutoff = self.mini_stream_cutoff() as u64;
+        let mut moves = vec![];
+        for (i, entry) in self.entries_ref()?.iter().enumerate() {
+            let size = entry.effective_size(&self.version);
+            if i != skip && entry.object_type == ObjectType::Stream && size > 0 && size < cutoff {
+                moves.push((i, self.read(entry)?, entry.chain.clone().unwrap_or_default()));
+            }
+        }
+        for (i, data, old) in moves {
+            let mut chain = vec![];
+            self.fill_chain(&mut chain, data, &mut std::io::empty(), &mut true)?;
+            self.free_chain(&old, false)?;
+            let entry = &mut self.entries_mut()?[i];
+            entry.starting_sector_location = chain.first().cloned().unwrap_or(SectorType::EndOfChain);
+            entry.chain = Some(chain);
+        }
+        Ok(())
+    }
+
+    /// allocate `pending` and whatever else `reader` holds onto the end of `chain`,
+    /// mini sectors when `mini`; returns the number of bytes written
+    ///
+    /// a mini chain keeps reading mini sector by mini sector while under the cutoff,
+    /// and once the stream reaches it, it's copied into regular sectors and `mini`
+    /// is cleared, so `chain` is always of the kind `mini` says
+    fn fill_chain(&mut self, chain: &mut Vec<SectorType>, mut pending: Vec<u8>, reader: &mut impl Read, mini: &mut bool) -> OleResult<u64> {
+        let mut total = 0u64;
+        if *mini {
+            let unit = self.mini_sector_size();
+            let cutoff = self.mini_stream_cutoff() as u64;
+            for chunk in pending.chunks(unit) {
+                self.append_mini_sector(chain, chunk)?;
+            }
+            total = pending.len() as u64;
+            let mut buf = vec![0; unit];
+            while total < cutoff && total.is_multiple_of(unit as u64) {
+                let len = read_full(reader, &mut buf)?;
+                if len == 0 {
+                    break;
+                }
+                self.append_mini_sector(chain, &buf[..len])?;
+                total += len as u64;
+            }
+            if total < cutoff {
+                return Ok(total);
+            }
+            let moved = std::mem::take(chain);
+            pending = match self.copy_mini_chain(&moved, total, chain) {
+                Ok(v) => v,
+                Err(err) => {
+                    self.free_chain(chain, false)?;
+                    *chain = moved;
+                    return Err(err);
+                }
+            };
+            self.free_chain(&moved, true)?;
+            *mini = false;
+            total -= pending.len() as u64;
+        }
+        let sector_size = self.header.sector_size();
+        let mut buf = vec![0; sector_size];
+        let mut eof = false;
+        loop {
+            while pending.len() >= sector_size || (eof && !pending.is_empty()) {
+                let len = std::cmp::min(sector_size, pending.len());
+                self.append_sector(chain, &pending[..len])?;
+                pending.drain(..len);
+                total += len as u64;
+            }
+            if eof {
+                return Ok(total);
+            }
+            let len = read_full(reader, &mut buf)?;
+            eof = len < sector_size;
+            pending.extend_from_slice(&buf[..len]);
+        }
+    }
+
+    /// copy the first `len` bytes of a mini chain into regular sectors appended to
+    /// `chain`, one sector at a time; returns the tail too short to fill a sector
+    fn copy_mini_chain(&mut self, mini_chain: &[SectorType], len: u64, chain: &mut Vec<SectorType>) -> OleResult<Vec<u8>> {
+        let sector_size = self.header.sector_size();
+        let unit = self.mini_sector_size();
+        let mut left = len as usize;
+        let mut buf = Vec::with_capacity(sector_size);
+        for sector in mini_chain {
+            let SectorType::RegularSect(idx) = sector else {
+                return Err(OleError::InvalidEntryChain);
+            };
+            let n = std::cmp::min(unit, left);
+            buf.extend_from_slice(&self.mini_sector(*idx)?[..n]);
+            left -= n;
+            if buf.len() == sector_size {
+                self.append_sector(chain, &buf)?;
+                buf.clear();
+            }
+        }
+        Ok(buf)
+    }
+
+    /// write `data` as the stream at `path`, creating it inside its parent storage if needed
+    ///
+    /// when the stream stays on the same side of the cutoff and needs no more sectors
+    /// than its chain already has, the chain is overwritten in place and only its
+    /// surplus tail is freed, so rewriting a stream doesn't fragment the file
+    pub fn write_stream(&mut self, path: &str, data: &[u8]) -> OleResult<()> {
+        self.settle()?;
+        let Some(idx) = self.find_stream(path)?.2 else {
+            return self.write_stream_from_reader(path, &mut Cursor::new(data)).map(|_| ());
+        };
+        let mini = data.len() < self.mini_stream_cutoff() as usize;
+        let unit = if mini { self.mini_sector_size() } else { self.header.sector_size() };
+        let needed = data.len().div_ceil(unit);
+        let entry = &self.entries_ref()?[idx];
+        let chain = entry.chain.clone().unwrap_or_default();
+        if needed == 0 || needed > chain.len() || self.in_mini_stream(entry) != mini {
+            return self.write_stream_from_reader(path, &mut Cursor::new(data)).map(|_| ());
+        }
+
+        let mut sectors = vec![];
+        for item in &chain {
+            match item {
+                SectorType::RegularSect(v) => sectors.push(*v),
+                _ => return Err(OleError::InvalidEntryChain),
+            }
+        }
+        for (sector, chunk) in sectors.iter().zip(data.chunks(unit)) {
+            let mut padded = chunk.to_vec();
+            padded.resize(unit, 0);
+            if mini {
+                self.write_mini_sector(*sector, &padded)?;
+            } else {
+                let buf = self.body.get_mut(*sector as usize).ok_or(OleError::InvalidEntryIndex)?;
+                *buf = padded;
+            }
+        }
+
+        let table = if mini { self.mini_fat.as_mut().ok_or(OleError::InvalidEntryChain)? } else { self.fat_mut()? };
+        for sector in &sectors[needed..] {
+            *table.get_mut(*sector as usize).ok_or(OleError::InvalidEntryIndex)? = SectorType::FreeSect;
+        }
+        table[sectors[needed - 1] as usize] = SectorType::EndOfChain;
+        if let Some(lowest) = sectors[needed..].iter().min().filter(|_| !mini) {
+            self.free_hint = std::cmp::min(self.free_hint, *lowest as usize);
+        }
+
+        let entry = &mut self.entries_mut()?[idx];
+        entry.stream_size = data.len() as u64;
+        entry.chain = Some(chain[..needed].to_vec());
+        self.flush_tables()
+    }
+
+    /// regenerate the FAT from the chains held in memory: the directory, the mini FAT,
+    /// the mini stream container and every regular stream are linked and terminated,
+    /// FAT and DIFAT sectors are marked, and every other sector becomes free
+    ///
+    /// this is the inverse of chain walking and lets a repair tool write back chains it
+    /// recovered by other means
+    pub fn rebuild_fat(&mut self) -> OleResult<()> {
+        self.settle()?;
+        let difat_sectors = self.difat_chain()?;
+        while self.fat_ref()?.len() < self.body.len() {
+            self.grow_fat()?;
+        }
+
+        let mut fat = vec![SectorType::FreeSect; self.fat_ref()?.len()];
+        let mut mark = |sector: usize, value: SectorType| -> OleResult<()> {
+            *fat.get_mut(sector).ok_or(OleError::InvalidEntryIndex)? = value;
+            Ok(())
+        };
+        for sector in &self.difat {
+            mark(u32::from(sector.clone()) as usize, SectorType::FatSect)?;
+        }
+        for sector in difat_sectors {
+            mark(sector, SectorType::DifSect)?;
+        }
+        let mut chains = vec![self.directory.clone().unwrap_or_default(), self.mini_fat_sectors.clone()];
+        for entry in self.entries_ref()? {
+            let regular = match entry.object_type {
+                ObjectType::RootStorage => true,
+                ObjectType::Stream => !self.in_mini_stream(entry),
+                _ => false,
+            };
+            if let (true, Some(chain)) = (regular, &entry.chain) {
+                chains.push(chain.clone());
+            }
+        }
+        for chain in chains {
+            for (i, sector) in chain.iter().enumerate() {
+                let next = chain.get(i + 1).cloned().unwrap_or(SectorType::EndOfChain);
+                mark(u32::from(sector.clone()) as usize, next)?;
+            }
+        }
+
+        self.fat = Some(fat);
+        self.free_hint = 0;
+        self.header.number_of_fat_sectors = self.difat.len() as u32;
+        self.flush_tables()
+    }
+
+    /// re-lay out the whole file with the sector size of another major version
+    ///
+    /// the directory, mini FAT, mini stream container and regular streams are copied
+    /// into freshly allocated sectors, the FAT and DIFAT are rebuilt from scratch and
+    /// the header counts and shifts are updated; mini sector numbering is unchanged
+    pub fn convert_version(&mut self, target: MajorVersion) -> OleResult<()> {
+        self.settle()?;
+        if target == self.version {
+            return Ok(());
+        }
+        let mut entries = self.entries_ref()?.clone();
+        let mut payloads = vec![];
+        for (i, entry) in entries.iter().enumerate() {
+            let regular = match entry.object_type {
+                ObjectType::RootStorage => true,
+                ObjectType::Stream => !self.in_mini_stream(entry),
+                _ => false,
+            };
+            // a chain falling short fails here, before anything is torn down
+            if regular {
+                payloads.push((i, self.read_contents(entry)?));
+            }
+        }
+        let mini_fat = self.mini_fat.take();
+
+        self.header.sector_shift = if target == MajorVersion::Version3 { 0x0009 } else { 0x000C };
+        self.header.major_version = target.clone();
+        self.version = target;
+        let sector_size = self.header.sector_size();
+        let per_sector = sector_size / 4;
+        let entries_per_sector = sector_size / 128;
+
+        self.body = vec![];
+        self.fat = Some(vec![]);
+        self.free_hint = 0;
+        self.difat = vec![];
+        self.header.number_of_fat_sectors = 0;
+        self.header.number_of_difat_sectors = 0;
+        self.header.first_difat_sector_location = SectorType::EndOfChain;
+
+        while entries.len() % entries_per_sector != 0 {
+            entries.push(Entry::unused());
+        }
+        let mut directory = vec![];
+        for _ in 0..entries.len() / entries_per_sector {
+            self.append_sector(&mut directory, &[])?;
+        }
+        self.header.first_directory_sector_location = directory.first().cloned().unwrap_or(SectorType::EndOfChain);
+        self.header.number_of_directory_sectors = if self.version == MajorVersion::Version4 { directory.len() as u32 } else { 0 };
+        self.directory = Some(directory);
+
+        let mut mini_fat = mini_fat.unwrap_or_default();
+        let used = mini_fat.iter().rposition(|v| v != &SectorType::FreeSect).map_or(0, |v| v + 1);
+        mini_fat.resize(used.div_ceil(per_sector) * per_sector, SectorType::FreeSect);
+        let mut mini_fat_sectors = vec![];
+        for _ in 0..mini_fat.len() / per_sector {
+            self.append_sector(&mut mini_fat_sectors, &[])?;
+        }
+        self.header.first_mini_fat_sector_location = mini_fat_sectors.first().cloned().unwrap_or(SectorType::EndOfChain);
+        self.header.number_of_mini_fat_sectors = mini_fat_sectors.len() as u32;
+        self.mini_fat_sectors = mini_fat_sectors;
+        self.mini_fat = (!mini_fat.is_empty()).then_some(mini_fat);
+
+        for (idx, data) in payloads {
+            let mut chain = vec![];
+            for chunk in data.chunks(sector_size) {
+                self.append_sector(&mut chain, chunk)?;
+            }
+            entries[idx].starting_sector_location = chain.first().cloned().unwrap_or(SectorType::EndOfChain);
+            entries[idx].chain = Some(chain);
+        }
+        self.entries = Some(entries);
+        self.flush_tables()
+    }
+
+    /// set the header's minor version, written out as given
+    pub fn set_minor_version(&mut self, v: u16) {
+        self.header.minor_version = v;
+    }
+
+    /// raw bytes of a body sector for patching in place; reads always go to `body`,
+    /// so there is nothing cached to go stale
+    pub fn sector_data_mut(&mut self, index: usize) -> OleResult<&mut [u8]> {
+        self.settle()?;
+        self.body.get_mut(index).map(|v| &mut v[..]).ok_or(OleError::InvalidEntryIndex)
+    }
+
+    pub(crate) fn entries_mut(&mut self) -> OleResult<&mut Vec<Entry>> {
+        self.settle()?;
+        self.entries.as_mut().ok_or(OleError::NotParsed)
+    }
+
+    pub(crate) fn fat_mut(&mut self) -> OleResult<&mut Vec<SectorType>> {
+        self.settle()?;
+        self.fat.as_mut().ok_or(OleError::NotParsed)
+    }
+
+    /// create a new stream holding `data`, failing if `path` already exists
+    pub fn insert_stream(&mut self, path: &str, data: &[u8]) -> OleResult<()> {
+        self.settle()?;
+        let (parent, name) = self.parent_of(path)?;
+        if self.find_child(parent, name)?.is_some() {
+            return Err(OleError::EntryExists(path.to_string()));
+        }
+        self.insert_entry(parent, Entry::new(name, ObjectType::Stream))?;
+        self.write_stream_from_reader(path, &mut Cursor::new(data)).map(|_| ())
+    }
+
+    /// create an empty storage, failing if `path` already exists
+    pub fn create_storage(&mut self, path: &str) -> OleResult<()> {
+        self.settle()?;
+        let (parent, name) = self.parent_of(path)?;
+        if self.find_child(parent, name)?.is_some() {
+            return Err(OleError::EntryExists(path.to_string()));
+        }
+        self.insert_entry(parent, Entry::new(name, ObjectType::Storage))?;
+        self.flush_tables()
+    }
+
+    /// copy every storage and stream of `other` into the storage at `path`, with fresh
+    /// sectors in this file; storages keep their CLSIDs
+    ///
+    /// fails with `EntryExists` before changing anything if one of `other`'s top
+    /// level names is already taken
+    pub fn merge_at(&mut self, path: &str, other: &Ole) -> OleResult<()> {
+        self.settle()?;
+        let target = self.path_index(path)?;
+        if !matches!(self.entries_ref()?[target].object_type, ObjectType::Storage | ObjectType::RootStorage) {
+            return Err(OleError::PathNotFound(path.to_string()));
+        }
+        let base = path.trim_matches('/');
+        let join = |child: &str| format!("{}/{}", base, child.trim_start_matches('/'));
+
+        let items = other.entry_paths()?;
+        let entries = other.entries_ref()?;
+        for (child, idx) in &items[1..] {
+            let name = entries[*idx].name();
+            if child.rsplit_once('/').is_some_and(|(parent, _)| parent.is_empty()) && self.find_child(target, &name)?.is_some() {
+                return Err(OleError::EntryExists(join(child)));
+            }
+        }
+        for (child, idx) in &items[1..] {
+            let entry = &entries[*idx];
+            match entry.object_type {
+                ObjectType::Storage => {
+                    self.create_storage(&join(child))?;
+                    let created = self.path_index(&join(child))?;
+                    self.entries_mut()?[created].clsid = entry.clsid;
+                }
+                ObjectType::Stream => {
+                    let data = other.read_contents(entry)?;
+                    self.insert_stream(&join(child), &data)?;
+                }
+                _ => {}
+            }
+        }
+        self.flush_tables()
+    }
+
+    /// delete a stream, freeing its sectors and its directory slot
+    pub fn remove_stream(&mut self, path: &str) -> OleResult<()> {
+        self.settle()?;
+        let (parent, name) = self.parent_of(path)?;
+        let idx = self.find_child(parent, name)?.ok_or_else(|| OleError::PathNotFound(path.to_string()))?;
+        if self.entries_ref()?[idx].object_type != ObjectType::Stream {
+            return Err(OleError::NotAStream(path.to_string()));
+        }
+        self.release_stream(idx)?;
+        self.unlink_entry(parent, idx)?;
+        self.entries_mut()?[idx] = Entry::unused();
+        self.flush_tables()
+    }
+
+    /// drop free sectors from the end of the file, along with FAT sectors that only
+    /// describe sectors past the new end and DIFAT sectors no longer needed to list
+    /// the FAT sectors left; free sectors in the middle are left alone
+    pub fn shrink_to_fit(&mut self) -> OleResult<()> {
+        self.settle()?;
+        let sector_size = self.header.sector_size();
+        let per_sector = sector_size / 4;
+        loop {
+            while self.body.len() > 1 && self.fat_ref()?.get(self.body.len() - 1) == Some(&SectorType::FreeSect) {
+                self.body.pop();
+            }
+            let difat_sectors = self.difat_chain()?;
+            if let Some((last, rest)) = difat_sectors.split_last() {
+                if self.difat.len() <= 109 + rest.len() * (per_sector - 1) {
+                    match rest.last() {
+                        Some(prev) => self.body[*prev][sector_size - 4..].copy_from_slice(&u32::from(SectorType::EndOfChain).to_le_bytes()),
+                        None => self.header.first_difat_sector_location = SectorType::EndOfChain,
+                    }
+                    self.fat_mut()?[*last] = SectorType::FreeSect;
+                    self.header.number_of_difat_sectors -= 1;
+                    continue;
+                }
+            }
+            // the last FAT sector goes once every sector it describes is free, apart
+            // from itself and a DIFAT sector that's only there to list it
+            let Some(SectorType::RegularSect(fat_sector)) = self.difat.last().cloned().filter(|_| self.difat.len() > 1) else {
+                break;
+            };
+            let spare_difat = difat_sectors.split_last()
+                .filter(|(_, rest)| self.difat.len() - 1 <= 109 + rest.len() * (per_sector - 1))
+                .map(|(v, _)| *v);
+            let start = (self.difat.len() - 1) * per_sector;
+            let end = std::cmp::min(start + per_sector, self.body.len());
+            let fat = self.fat_ref()?;
+            let in_use = (start..end).any(|i| {
+                i != fat_sector as usize && Some(i) != spare_difat && fat.get(i) != Some(&SectorType::FreeSect)
+            });
+            if in_use {
+                break;
+            }
+            self.difat.pop();
+            self.fat_mut()?[fat_sector as usize] = SectorType::FreeSect;
+            self.header.number_of_fat_sectors -= 1;
+        }
+        let len = self.difat.len() * per_sector;
+        self.fat_mut()?.truncate(len);
+        self.flush_tables()
+    }
+
+    /// directory slots that hold no object and can be reused for new entries
+    pub fn free_directory_slots(&self) -> Vec<usize> {
+        self.entries_ref().into_iter()
+            .flatten()
+            .enumerate()
+            .skip(1)
+            .filter(|(_, v)| v.object_type == ObjectType::Unknown)
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// split a path into its parent storage and a validated leaf name
+    fn parent_of<'p>(&self, path: &'p str) -> OleResult<(usize, &'p str)> {
+        let trimmed = path.trim_matches('/');
+        let (parent_path, name) = trimmed.rsplit_once('/').unwrap_or(("", trimmed));
+        validate_name(name)?;
+        let parent = self.path_index(parent_path)?;
+        match self.entries_ref()?[parent].object_type {
+            ObjectType::Storage | ObjectType::RootStorage => Ok((parent, name)),
+            _ => Err(OleError::PathNotFound(path.to_string())),
+        }
+    }
+
+    /// the parent storage and leaf name of `path`, with the stream already there if any
+    fn find_stream<'p>(&self, path: &'p str) -> OleResult<(usize, &'p str, Option<usize>)> {
+        let (parent, name) = self.parent_of(path)?;
+        match self.find_child(parent, name)? {
+            Some(idx) if self.entries_ref()?[idx].object_type == ObjectType::Stream => Ok((parent, name, Some(idx))),
+            Some(_) => Err(OleError::NotAStream(path.to_string())),
+            None => Ok((parent, name, None)),
+        }
+    }
+
+    /// free the sectors held by a stream and reset it to empty
+    fn release_stream(&mut self, idx: usize) -> OleResult<()> {
+        let is_mini = self.in_mini_stream(&self.entries_ref()?[idx]);
+        let entry = &mut self.entries_mut()?[idx];
+        let chain = entry.chain.take().unwrap_or_default();
+        entry.stream_size = 0;
+        entry.starting_sector_location = SectorType::EndOfChain;
+        self.free_chain(&chain, is_mini)
+    }
+
+    /// mark every sector of a chain free in the FAT, or the mini FAT when `mini`
+    fn free_chain(&mut self, chain: &[SectorType], mini: bool) -> OleResult<()> {
+        if chain.is_empty() {
+            return Ok(());
+        }
+        let table = if mini {
+            self.mini_fat.as_mut().ok_or(OleError::InvalidEntryChain)?
+        } else {
+            self.fat_mut()?
+        };
+        for sector in chain {
+            if let SectorType::RegularSect(v) = sector {
+                let slot = table.get_mut(*v as usize).ok_or(OleError::InvalidEntryIndex)?;
+                *slot = SectorType::FreeSect;
+            }
+        }
+        let lowest = chain.iter().filter_map(|v| match v {
+            SectorType::RegularSect(v) => Some(*v as usize),
+            _ => None,
+        }).min();
+        if let Some(lowest) = lowest.filter(|_| !mini) {
+            self.free_hint = std::cmp::min(self.free_hint, lowest);
+        }
+        Ok(())
+    }
+
+    /// place a new entry in the directory and link it into the parent's red-black tree,
+    /// claiming a free slot before growing the directory by a sector
+    pub(crate) fn insert_entry(&mut self, parent: usize, entry: Entry) -> OleResult<usize> {
+        if let Some(idx) = self.free_directory_slots().first().cloned() {
+            self.entries_mut()?[idx] = entry;
+            self.link_entry(parent, idx)?;
+            return Ok(idx);
+        }
+
+        let per_sector = self.header.sector_size() / 128;
+        let sector = self.allocate_sector()?;
+        let last = self.directory.as_ref().and_then(|v| v.last().cloned());
+        if let Some(SectorType::RegularSect(last)) = last {
+            self.fat_mut()?[last as usize] = SectorType::RegularSect(sector);
+        }
+        self.directory.get_or_insert_with(Vec::new).push(SectorType::RegularSect(sector));
+        if self.version == MajorVersion::Version4 {
+            self.header.number_of_directory_sectors += 1;
+        }
+
+        let entries = self.entries_mut()?;
+        let idx = entries.len();
+        entries.push(entry);
+        entries.extend((1..per_sector).map(|_| Entry::unused()));
+        self.link_entry(parent, idx)?;
+        Ok(idx)
+    }
+
+    /// add an entry to the parent's sibling tree, which is then relinked balanced
+    fn link_entry(&mut self, parent: usize, idx: usize) -> OleResult<()> {
+        let entries = self.entries_mut()?;
+        let mut children = in_order(entries, entries[parent].child_id.clone())?;
+        let name = entries[idx].name().into_owned();
+        match children.binary_search_by(|v| compare_names(&entries[*v].name(), &name)) {
+            Ok(_) => return Err(OleError::InvalidEntryName(name)),
+            Err(pos) => children.insert(pos, idx),
+        }
+        entries[parent].child_id = balance(entries, &children, 0, depth_of(children.len()));
+        Ok(())
+    }
+
+    /// take an entry out of its parent's sibling tree, which is then relinked balanced
+    fn unlink_entry(&mut self, parent: usize, idx: usize) -> OleResult<()> {
+        let entries = self.entries_mut()?;
+        let mut children = in_order(entries, entries[parent].child_id.clone())?;
+        let pos = children.iter().position(|v| *v == idx).ok_or(OleError::InvalidEntryChain)?;
+        children.remove(pos);
+        entries[parent].child_id = balance(entries, &children, 0, depth_of(children.len()));
+        Ok(())
+    }
+
+    /// allocate a zeroed regular sector, reusing free sectors first and growing the FAT when full
+    ///
+    /// the search starts at `free_hint`, below which nothing is free, so appending
+    /// sector after sector doesn't rescan the FAT from the start each time
+    pub(crate) fn allocate_sector(&mut self) -> OleResult<u32> {
+        let sector_size = self.header.sector_size();
+        let body_len = self.body.len();
+        let hint = self.free_hint;
+        let free = self.fat_mut()?.iter().enumerate().take(body_len).skip(hint)
+            .find(|(_, v)| *v == &SectorType::FreeSect)
+            .map(|(i, _)| i);
+        let idx = match free {
+            Some(idx) => idx,
+            None => {
+                while self.body.len() >= self.fat_mut()?.len() {
+                    self.grow_fat()?;
+                }
+                self.body.push(vec![]);
+                self.body.len() - 1
+            }
+        };
+        self.body[idx] = vec![0; sector_size];
+        self.fat_mut()?[idx] = SectorType::EndOfChain;
+        self.free_hint = idx + 1;
+        Ok(idx as u32)
+    }
+
+    /// append a FAT sector at the end of the body, preceded by a DIFAT sector when the
+    /// header and the existing DIFAT sectors have no slot left for it
+    fn grow_fat(&mut self) -> OleResult<()> {
+        let sector_size = self.header.sector_size();
+        let per_sector = sector_size / 4;
+        let capacity = 109 + self.header.number_of_difat_sectors as usize * (per_sector - 1);
+        let difat_sector = match self.difat.len() >= capacity {
+            true => Some(self.grow_difat()?),
+            false => None,
+        };
+        let idx = self.body.len();
+        self.body.push(vec![0xFF; sector_size]);
+        let fat = self.fat_mut()?;
+        fat.extend((0..per_sector).map(|_| SectorType::FreeSect));
+        if idx >= fat.len() {
+            fat.resize(idx + 1, SectorType::FreeSect);
+        }
+        fat[idx] = SectorType::FatSect;
+        if let Some(v) = difat_sector {
+            fat[v] = SectorType::DifSect;
+        }
+        self.difat.push(SectorType::RegularSect(idx as u32));
+        self.header.number_of_fat_sectors += 1;
+        Ok(())
+    }
+
+    /// append an empty DIFAT sector at the end of the body and link it to the end of
+    /// the DIFAT chain; its FAT entry is left to the caller, which is about to grow
+    /// the FAT anyway
+    fn grow_difat(&mut self) -> OleResult<usize> {
+        let sector_size = self.header.sector_size();
+        let chain = self.difat_chain()?;
+        let idx = self.body.len();
+        let mut buf = vec![0xFF; sector_size];
+        buf[sector_size - 4..].copy_from_slice(&u32::from(SectorType::EndOfChain).to_le_bytes());
+        self.body.push(buf);
+        match chain.last() {
+            Some(prev) => self.body[*prev][sector_size - 4..].copy_from_slice(&(idx as u32).to_le_bytes()),
+            None => self.header.first_difat_sector_location = SectorType::RegularSect(idx as u32),
+        }
+        self.header.number_of_difat_sectors += 1;
+        Ok(idx)
+    }
+
+    /// allocate a sector holding `data` and link it to the end of `chain`
+    pub(crate) fn append_sector(&mut self, chain: &mut Vec<SectorType>, data: &[u8]) -> OleResult<u32> {
+        let sector = self.allocate_sector()?;
+        self.body[sector as usize][..data.len()].copy_from_slice(data);
+        if let Some(SectorType::RegularSect(prev)) = chain.last() {
+            self.fat_mut()?[*prev as usize] = SectorType::RegularSect(sector);
+        }
+        chain.push(SectorType::RegularSect(sector));
+        Ok(sector)
+    }
+
+    /// allocate a mini sector holding `data` and link it to the end of `chain`
+    pub(crate) fn append_mini_sector(&mut self, chain: &mut Vec<SectorType>, data: &[u8]) -> OleResult<u32> {
+        let sector = self.allocate_mini_sector()?;
+        self.write_mini_sector(sector, data)?;
+        if let Some(SectorType::RegularSect(prev)) = chain.last() {
+            let mini_fat = self.mini_fat.as_mut().ok_or(OleError::InvalidEntryChain)?;
+            mini_fat[*prev as usize] = SectorType::RegularSect(sector);
+        }
+        chain.push(SectorType::RegularSect(sector));
+        Ok(sector)
+    }
+
+    /// allocate a mini sector, growing the mini FAT and the mini stream container as needed
+    fn allocate_mini_sector(&mut self) -> OleResult<u32> {
+        let sector_size = self.header.sector_size();
+        let per_sector = sector_size / 4;
+        let mini_sector_size = self.mini_sector_size();
+
+        let free = self.mini_fat.as_ref()
+            .and_then(|v| v.iter().position(|v| v == &SectorType::FreeSect));
+        let idx = match free {
+            Some(idx) => idx,
+            None => {
+                let sector = self.allocate_sector()?;
+                match self.mini_fat_sectors.last() {
+                    Some(SectorType::RegularSect(last)) => {
+                        let last = *last as usize;
+                        self.fat_mut()?[last] = SectorType::RegularSect(sector);
+                    }
+                    _ => self.header.first_mini_fat_sector_location = SectorType::RegularSect(sector),
+                }
+                self.mini_fat_sectors.push(SectorType::RegularSect(sector));
+                self.header.number_of_mini_fat_sectors += 1;
+                let mini_fat = self.mini_fat.get_or_insert_with(Vec::new);
+                let idx = mini_fat.len();
+                mini_fat.extend((0..per_sector).map(|_| SectorType::FreeSect));
+                idx
+            }
+        };
+        self.mini_fat.as_mut().ok_or(OleError::InvalidEntryChain)?[idx] = SectorType::EndOfChain;
+
+        let end = (idx + 1) * mini_sector_size;
+        let mut container = self.entries_mut()?.first_mut().ok_or(OleError::InvalidEntryIndex)?
+            .chain.take().unwrap_or_default();
+        let result = (|| {
+            while container.len() * sector_size < end {
+                self.append_sector(&mut container, &[])?;
+            }
+            Ok(())
+        })();
+        let root = &mut self.entries_mut()?[0];
+        root.starting_sector_location = container.first().cloned().unwrap_or(SectorType::EndOfChain);
+        root.chain = Some(container);
+        root.stream_size = std::cmp::max(root.stream_size, end as u64);
+        result.map(|_| idx as u32)
+    }
+
+    /// the bytes of a mini sector, read through the mini stream container
+    fn mini_sector(&self, idx: u32) -> OleResult<&[u8]> {
+        let sector_size = self.header.sector_size();
+        let unit = self.mini_sector_size();
+        let offset = idx as usize * unit;
+        let container = self.entries_ref()?[0].chain.as_ref().ok_or(OleError::InvalidEntryChain)?;
+        let SectorType::RegularSect(sector) = *container.get(offset / sector_size).ok_or(OleError::InvalidEntryChain)? else {
+            return Err(OleError::InvalidEntryChain);
+        };
+        let start = offset % sector_size;
+        self.body.get(sector as usize)
+            .and_then(|v| v.get(start..start + unit))
+            .ok_or(OleError::InvalidEntryIndex)
+    }
+
+    fn write_mini_sector(&mut self, idx: u32, data: &[u8]) -> OleResult<()> {
+        let sector_size = self.header.sector_size();
+        let offset = idx as usize * self.mini_sector_size();
+        let container = self.entries_ref()?[0].chain.as_ref().ok_or(OleError::InvalidEntryChain)?;
+        let SectorType::RegularSect(sector) = *container.get(offset / sector_size).ok_or(OleError::InvalidEntryChain)? else {
+            return Err(OleError::InvalidEntryChain);
+        };
+        let buf = self.body.get_mut(sector as usize).ok_or(OleError::InvalidEntryIndex)?;
+        buf.resize(sector_size, 0);
+        let start = offset % sector_size;
+        buf[start..start + data.len()].copy_from_slice(data);
+        Ok(())
+    }
+
+    /// write the DIFAT, FAT, mini FAT and directory back into their sectors
+    pub(crate) fn flush_tables(&mut self) -> OleResult<()> {
+        let sector_size = self.header.sector_size();
+        let per_sector = sector_size / 4;
+        let mut writes = vec![];
+
+        // the header holds the first 109 DIFAT entries, each DIFAT sector the next
+        // `per_sector - 1` followed by the pointer to the next DIFAT sector
+        let difat_sectors = self.difat_chain()?;
+        for (i, sector) in difat_sectors.iter().enumerate() {
+            let start = 109 + i * (per_sector - 1);
+            let mut entries = self.difat.iter().skip(start).take(per_sector - 1).cloned().collect::<Vec<SectorType>>();
+            entries.resize(per_sector - 1, SectorType::FreeSect);
+            let next = difat_sectors.get(i + 1).map_or(SectorType::EndOfChain, |v| SectorType::RegularSect(*v as u32));
+            let mut cursor = Cursor::new(vec![]);
+            Difat { entries, next }.write_le_args(&mut cursor, ((per_sector - 1) as u16,))?;
+            writes.push((*sector as u32, cursor.into_inner()));
+        }
+
+        let fat = self.fat_ref()?;
+        for (i, sector) in self.difat.iter().enumerate() {
+            if let SectorType::RegularSect(v) = sector {
+                let entries = fat.iter().skip(i * per_sector).take(per_sector).cloned().collect::<Vec<SectorType>>();
+                let mut cursor = Cursor::new(vec![]);
+                Fat { entries }.write_le(&mut cursor)?;
+                writes.push((*v, cursor.into_inner()));
+            }
+        }
+
+        if let Some(mini_fat) = &self.mini_fat {
+            for (i, sector) in self.mini_fat_sectors.iter().enumerate() {
+                if let SectorType::RegularSect(v) = sector {
+                    let entries = mini_fat.iter().skip(i * per_sector).take(per_sector).cloned().collect::<Vec<SectorType>>();
+                    let mut cursor = Cursor::new(vec![]);
+                    MiniFat { entries }.write_le_args(&mut cursor, (per_sector as u16,))?;
+                    writes.push((*v, cursor.into_inner()));
+                }
+            }
+        }
+
+        writes.extend(self.directory_sectors()?);
+
+        for (sector, data) in writes {
+            let buf = self.body.get_mut(sector as usize).ok_or(OleError::InvalidEntryIndex)?;
+            buf.resize(sector_size, 0);
+            buf[..data.len()].copy_from_slice(&data);
+        }
+        self.directory_dirty = false;
+        Ok(())
+    }
+
+    /// the directory entries serialized into the sectors of the directory chain
+    fn directory_sectors(&self) -> OleResult<Vec<(u32, Vec<u8>)>> {
+        let mut writes = vec![];
+        if let (Some(directory), Some(entries)) = (&self.directory, &self.entries) {
+            let per_sector = self.header.sector_size() / 128;
+            for (sector, chunk) in directory.iter().zip(entries.chunks(per_sector)) {
+                if let SectorType::RegularSect(v) = sector {
+                    let mut cursor = Cursor::new(vec![]);
+                    Directory { entries: chunk.to_vec() }.write_le_args(&mut cursor, (per_sector as u16,))?;
+                    writes.push((*v, cursor.into_inner()));
+                }
+            }
+        }
+        Ok(writes)
+    }
+
+    /// the entry at `path` for editing in place, its name, CLSID, state bits or
+    /// timestamps say; the change is picked up by the next `to_writer` or edit,
+    /// but renaming also has to keep the sibling tree in order, which this doesn't
+    pub fn entry_by_path_mut(&mut self, path: &str) -> OleResult<&mut Entry> {
+        let idx = self.path_index(path)?;
+        self.directory_dirty = true;
+        Ok(&mut self.entries_mut()?[idx])
+    }
+
+    /// serialize the file: the header with the first 109 DIFAT entries, padded to a
+    /// full sector, then every sector of `body` as it stands, the DIFAT sectors holding
+    /// the rest among them
+    ///
+    /// edits keep the tables in `body` up to date, so an unchanged file comes out
+    /// byte for byte as it was read
+    pub fn to_writer<W: Write>(&self, writer: &mut W) -> OleResult<()> {
+        let mut header = Cursor::new(vec![]);
+        self.header.write_le(&mut header)?;
+        let entries = std::array::from_fn(|i| self.difat.get(i).cloned().unwrap_or(SectorType::FreeSect));
+        AllEntryDifat { entries }.write_le(&mut header)?;
+        let mut header = header.into_inner();
+        header.resize(self.header.sector_size(), 0);
+        writer.write_all(&header)?;
+        let directory = if self.directory_dirty {
+            self.directory_sectors()?.into_iter().map(|(k, v)| (k as usize, v)).collect()
+        } else {
+            HashMap::new()
+        };
+        for (i, sector) in self.body.iter().enumerate() {
+            match directory.get(&i) {
+                Some(data) => {
+                    writer.write_all(data)?;
+                    writer.write_all(&vec![0; sector.len().saturating_sub(data.len())])?;
+                }
+                None => writer.write_all(sector)?,
+            }
+        }
+        Ok(())
+    }
+
+    /// the whole file as bytes, see `to_writer`
+    pub fn to_bytes(&self) -> OleResult<Vec<u8>> {
+        let mut out = Cursor::new(vec![]);
+        self.to_writer(&mut out)?;
+        Ok(out.into_inner())
+    }
+
+    /// write the whole file to `path`, see `to_writer`
+    pub fn save_to_path(&self, path: &str) -> OleResult<()> {
+        Ok(fs::write(path, self.to_bytes()?)?)
+    }
+}
+
+/// the spec limits names to 31 UTF-16 code units and forbids `/ \ : !`
+fn validate_name(name: &str) -> OleResult<()> {
+    if name.is_empty() || name.encode_utf16().count() > 31 || name.contains(ILLEGAL_NAME_CHARS) {
+        return Err(OleError::InvalidEntryName(name.to_string()));
+    }
+    Ok(())
+}
+
+/// the entries of a sibling tree in name order; a tree holding more nodes than the
+/// directory has entries must loop
+fn in_order(entries: &[Entry], root: SectorType) -> OleResult<Vec<usize>> {
+    let mut result = vec![];
+    let mut stack = vec![];
+    let mut cur = root;
+    loop {
+        while let SectorType::RegularSect(v) = cur {
+            if stack.len() + result.len() >= entries.len() {
+                return Err(OleError::InvalidEntryChain);
+            }
+            stack.push(v as usize);
+            cur = entries.get(v as usize).ok_or(OleError::InvalidEntryIndex)?.left_sibling_id.clone();
+        }
+        let Some(idx) = stack.pop() else { break };
+        result.push(idx);
+        cur = entries[idx].right_sibling_id.clone();
+    }
+    Ok(result)
+}
+
+/// depth of the deepest level of a tree of `len` nodes built by `balance`
+fn depth_of(len: usize) -> usize {
+    (usize::BITS - len.leading_zeros()).saturating_sub(1) as usize
+}
+
+/// link `sorted` into a balanced tree and return its root
+///
+/// every path to a leaf ends at depth `last` or `last - 1`, so coloring just the
+/// nodes at depth `last` red gives every path the same number of black nodes; the
+/// root is always black
+fn balance(entries: &mut [Entry], sorted: &[usize], depth: usize, last: usize) -> SectorType {
+    if sorted.is_empty() {
+        return SectorType::FreeSect;
+    }
+    let mid = sorted.len() / 2;
+    let left = balance(entries, &sorted[..mid], depth + 1, last);
+    let right = balance(entries, &sorted[mid + 1..], depth + 1, last);
+    let entry = &mut entries[sorted[mid]];
+    entry.left_sibling_id = left;
+    entry.right_sibling_id = right;
+    entry.color = if depth == last && depth > 0 { Color::Red } else { Color::Black };
+    SectorType::RegularSect(sorted[mid] as u32)
+}
+
+/// read until `buf` is full or the reader is exhausted
+fn read_full(reader: &mut impl Read, buf: &mut [u8]) -> OleResult<usize> {
+    let mut total = 0;
+    while total < buf.len() {
+        match reader.read(&mut buf[total..]) {
+            Ok(0) => break,
+            Ok(n) => total += n,
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::{MajorVersion, OleError, SectorType};
+    use crate::directory::{Color, ObjectType};
+    use crate::ole::{Ole, StreamLocator, SPEC_MINI_STREAM_CUTOFF};
+    use crate::test_util::*;
+    use std::io::{Cursor, Read};
+
+    #[test]
+    fn to_bytes_round_trips() {
+        let original = std::fs::read("./abcd.doc").unwrap();
+        let ole = Ole::open("./abcd.doc").unwrap();
+        assert_eq!(ole.to_bytes().unwrap(), original);
+
+        let bytes = build(vec![
+            stream("WordDocument", pattern(6144, 7)),
+            storage("Storage", vec![stream("Small", pattern(100, 1))]),
+        ]);
+        let mut ole = open(&bytes);
+        assert_eq!(ole.to_bytes().unwrap(), bytes);
+
+        ole.write_stream("WordDocument", &[0; 6144]).unwrap();
+        let saved = open(&ole.to_bytes().unwrap());
+        assert_eq!(saved.read_stream("WordDocument").unwrap(), vec![0; 6144]);
+        assert_eq!(saved.read_stream("/Storage/Small").unwrap(), pattern(100, 1));
+        assert!(saved.well_formed());
+    }
+
+    #[test]
+    fn to_bytes_writes_difat_sectors() {
+        let mut ole = open(&build(vec![stream("WordDocument", pattern(6144, 7))]));
+        // past the 109 * 128 sectors the header's DIFAT can map
+        let large = pattern(14000 * 512, 3);
+        ole.write_stream("Large", &large).unwrap();
+        assert_eq!(ole.header.number_of_difat_sectors, 1);
+
+        let saved = open(&ole.to_bytes().unwrap());
+        assert_eq!(saved.header.number_of_difat_sectors, 1);
+        assert_eq!(saved.header.number_of_fat_sectors as usize, saved.difat.len());
+        assert!(saved.difat.len() > 109);
+        assert_eq!(saved.difat, ole.difat);
+        let difat_sector = saved.difat_chain().unwrap()[0];
+        assert_eq!(saved.fat_entry(difat_sector).unwrap(), SectorType::DifSect);
+        assert_eq!(saved.read_stream("Large").unwrap(), large);
+        assert_eq!(saved.read_stream("WordDocument").unwrap(), pattern(6144, 7));
+        assert_eq!(saved.verify().unwrap(), vec![]);
+    }
+
+    #[test]
+    fn entry_by_path_mut() {
+        let mut ole = open(&build(vec![
+            stream("WordDocument", pattern(6144, 7)),
+            storage("Storage", vec![stream("Small", pattern(100, 1))]),
+        ]));
+        let before = ole.to_bytes().unwrap();
+        let entry = ole.entry_by_path_mut("/Storage").unwrap();
+        entry.clsid = [9; 16];
+        entry.modified_time = 128790414900000000;
+        ole.entry_by_path_mut("Storage/Small").unwrap().state_bits = 7;
+        assert!(matches!(ole.entry_by_path_mut("/Missing"), Err(OleError::PathNotFound(_))));
+
+        let bytes = ole.to_bytes().unwrap();
+        assert_eq!(bytes.len(), before.len());
+        let saved = open(&bytes);
+        let storage = saved.resolve_path("/Storage").unwrap();
+        assert_eq!(storage.clsid, [9; 16]);
+        assert_eq!(storage.modified_time, 128790414900000000);
+        assert_eq!(saved.resolve_path("/Storage/Small").unwrap().state_bits, 7);
+        assert_eq!(saved.read_stream("/Storage/Small").unwrap(), pattern(100, 1));
+
+        // an edit writes the directory into `body` as well
+        ole.write_stream("WordDocument", &[0; 6144]).unwrap();
+        assert!(!ole.directory_dirty);
+        assert_eq!(open(&ole.to_bytes().unwrap()).resolve_path("/Storage").unwrap().clsid, [9; 16]);
+    }
+
+    #[test]
+    fn write_stream_from_reader() {
+        let mut ole = open(&build(vec![
+            stream("WordDocument", pattern(6144, 7)),
+            stream("Small", pattern(100, 1)),
+        ]));
+        let data = pattern(5000, 9);
+        let written = ole.write_stream_from_reader("Data", &mut Cursor::new(data.clone())).unwrap();
+        assert_eq!(written, 5000);
+
+        let idx = ole.path_index("Data").unwrap();
+        let entry = &ole.entries.as_ref().unwrap()[idx];
+        assert_eq!(entry.stream_size, 5000);
+        assert_eq!(entry.chain.as_ref().unwrap().len(), 10);
+        assert_eq!(ole.read(entry).unwrap(), data);
+
+        // the original stream is untouched and can be replaced in place
+        let idx = ole.path_index("WordDocument").unwrap();
+        assert_eq!(ole.read(&ole.entries.as_ref().unwrap()[idx]).unwrap(), pattern(6144, 7));
+        ole.write_stream_from_reader("WordDocument", &mut Cursor::new(data.clone())).unwrap();
+        assert_eq!(ole.read(&ole.entries.as_ref().unwrap()[idx]).unwrap(), data);
+    }
+
+    #[test]
+    fn write_small_stream_from_reader_uses_mini_stream() {
+        let mut ole = open(&build(vec![stream("Small", pattern(100, 1))]));
+        let written = ole.write_stream_from_reader("Tiny", &mut Cursor::new(pattern(130, 4))).unwrap();
+        assert_eq!(written, 130);
+        let idx = ole.path_index("Tiny").unwrap();
+        let entry = &ole.entries.as_ref().unwrap()[idx];
+        assert_eq!(entry.chain.as_ref().unwrap().len(), 3);
+        assert_eq!(ole.mini_fat.as_ref().unwrap().iter().filter(|v| **v != SectorType::FreeSect).count(), 5);
+    }
+
+    #[test]
+    fn write_stream_reuses_chain() {
+        let mut ole = open(&build(vec![
+            stream("WordDocument", pattern(6144, 7)),
+            stream("Small", pattern(300, 1)),
+        ]));
+        let chain_of = |ole: &crate::ole::Ole, path: &str| path.locate(ole).unwrap().chain.clone().unwrap();
+        let big = chain_of(&ole, "WordDocument");
+        let small = chain_of(&ole, "Small");
+        let fat = ole.fat.clone();
+
+        ole.write_stream("WordDocument", &pattern(6100, 3)).unwrap();
+        ole.write_stream("Small", &pattern(290, 4)).unwrap();
+        assert_eq!(chain_of(&ole, "WordDocument"), big);
+        assert_eq!(chain_of(&ole, "Small"), small);
+        assert_eq!(ole.fat, fat);
+        assert_eq!(ole.read_stream("WordDocument").unwrap(), pattern(6100, 3));
+        assert_eq!(ole.read_stream("Small").unwrap(), pattern(290, 4));
+
+        // shorter data keeps the head of the chain and frees the rest
+        ole.write_stream("WordDocument", &pattern(4600, 5)).unwrap();
+        assert_eq!(chain_of(&ole, "WordDocument"), big[..9]);
+        assert_eq!(ole.fat_entry(u32::from(big[9].clone()) as usize).unwrap(), SectorType::FreeSect);
+        assert_eq!(ole.read_stream("WordDocument").unwrap(), pattern(4600, 5));
+        assert!(ole.well_formed());
+
+        // growing needs fresh sectors
+        ole.write_stream("Small", &pattern(600, 6)).unwrap();
+        assert_eq!(ole.read_stream("Small").unwrap(), pattern(600, 6));
+        assert!(ole.well_formed());
+    }
+
+    #[test]
+    fn rebuild_fat() {
+        let mut ole = open(&build(vec![
+            stream("WordDocument", pattern(6144, 7)),
+            storage("Storage", vec![stream("Small", pattern(100, 1))]),
+        ]));
+        let original = ole.fat.clone();
+        ole.fat.as_mut().unwrap().iter_mut().for_each(|v| *v = SectorType::FreeSect);
+        assert!(!ole.well_formed());
+
+        ole.rebuild_fat().unwrap();
+        assert_eq!(ole.fat, original);
+        assert!(ole.well_formed());
+        let entry = &ole.entries.as_ref().unwrap()[1];
+        assert_eq!(ole.read(entry).unwrap(), pattern(6144, 7));
+    }
+
+    #[test]
+    fn convert_version() {
+        let mut ole = open(&build(vec![
+            stream("WordDocument", pattern(6144, 7)),
+            storage("Storage", vec![stream("Small", pattern(100, 1))]),
+        ]));
+        let streams = ole.read_all_streams().unwrap();
+        ole.convert_version(MajorVersion::Version4).unwrap();
+        assert_eq!(ole.header.sector_shift, 12);
+        assert_eq!(ole.header.number_of_directory_sectors, 1);
+        assert!(ole.body.iter().all(|v| v.len() == 4096));
+        assert_eq!(ole.verify().unwrap(), vec![]);
+        assert_eq!(ole.read_range_by_id(1, 0, 6144).unwrap(), pattern(6144, 7));
+        assert_eq!(ole.read_range_by_id(3, 0, 100).unwrap(), pattern(100, 1));
+
+        // the written file parses on its own and reads the same
+        let mut saved = Ole::from_slice(&ole.to_bytes().unwrap()).unwrap();
+        saved.parse().unwrap();
+        assert_eq!(saved.version, MajorVersion::Version4);
+        assert_eq!(saved.read_all_streams().unwrap(), streams);
+        assert_eq!(saved.verify().unwrap(), vec![]);
+
+        ole.convert_version(MajorVersion::Version3).unwrap();
+        assert_eq!(ole.header.sector_shift, 9);
+        assert_eq!(ole.header.number_of_directory_sectors, 0);
+        assert_eq!(ole.verify().unwrap(), vec![]);
+        assert_eq!(ole.read_range_by_id(1, 0, 6144).unwrap(), pattern(6144, 7));
+        assert_eq!(ole.read_range_by_id(3, 0, 100).unwrap(), pattern(100, 1));
+
+        let mut saved = Ole::from_slice(&ole.to_bytes().unwrap()).unwrap();
+        saved.parse().unwrap();
+        assert_eq!(saved.version, MajorVersion::Version3);
+        assert_eq!(saved.read_all_streams().unwrap(), streams);
+        assert_eq!(saved.verify().unwrap(), vec![]);
+
+        // a stream whose chain ends early stops the conversion with the file untouched
+        let mut ole = open(&build(vec![stream("WordDocument", pattern(6144, 7)), stream("Small", pattern(100, 1))]));
+        ole.entries.as_mut().unwrap()[1].chain.as_mut().unwrap().truncate(3);
+        let body = ole.body.clone();
+        assert!(ole.convert_version(MajorVersion::Version4).is_err());
+        assert_eq!(ole.version, MajorVersion::Version3);
+        assert_eq!(ole.body, body);
+        assert_eq!(ole.read_stream("Small").unwrap(), pattern(100, 1));
+    }
+
+    #[test]
+    fn insert_reuses_free_directory_slots() {
+        let mut ole = open(&build(vec![stream("WordDocument", pattern(6144, 7))]));
+        assert_eq!(ole.free_directory_slots(), vec![2, 3]);
+
+        ole.create_storage("Storage").unwrap();
+        ole.insert_stream("Storage/Data", &pattern(5000, 2)).unwrap();
+        assert_eq!(ole.directory.as_ref().unwrap().len(), 1);
+        assert!(ole.free_directory_slots().is_empty());
+        assert_eq!(ole.read_stream("Storage/Data").unwrap(), pattern(5000, 2));
+        assert!(ole.insert_stream("Storage/Data", &[1]).is_err());
+
+        // with no slot left the directory grows by a sector
+        ole.insert_stream("More", &pattern(5000, 3)).unwrap();
+        assert_eq!(ole.directory.as_ref().unwrap().len(), 2);
+        assert_eq!(ole.free_directory_slots(), vec![5, 6, 7]);
+        assert_eq!(ole.verify().unwrap(), vec![]);
+    }
+
+    #[test]
+    fn inserts_keep_sibling_trees_balanced() {
+        let mut ole = open(&build(vec![stream("WordDocument", pattern(6144, 7))]));
+        ole.create_storage("Storage").unwrap();
+        for i in 0..20 {
+            ole.insert_stream(&format!("S{:02}", i), &pattern(10, i as u8)).unwrap();
+            ole.insert_stream(&format!("Storage/S{:02}", 19 - i), &pattern(10, i as u8)).unwrap();
+        }
+        let entries = ole.entries.as_ref().unwrap();
+        for entry in entries.iter().filter(|v| matches!(v.object_type, ObjectType::Storage | ObjectType::RootStorage)) {
+            if let SectorType::RegularSect(v) = entry.child_id {
+                assert_eq!(entries[v as usize].color, Color::Black);
+            }
+            assert!(black_height(entries, &entry.child_id).is_some());
+        }
+        for i in 0..20 {
+            assert_eq!(ole.read_stream(format!("Storage/S{:02}", 19 - i).as_str()).unwrap(), pattern(10, i as u8));
+        }
+        assert_eq!(ole.verify().unwrap(), vec![]);
+    }
+
+    #[test]
+    fn sector_data_mut() {
+        let mut ole = open(&build(vec![stream("WordDocument", pattern(6144, 7))]));
+        // FAT, directory, then WordDocument from sector 2
+        ole.sector_data_mut(3).unwrap()[10] = 0xAB;
+        let mut expected = pattern(6144, 7);
+        expected[512 + 10] = 0xAB;
+        assert_eq!(ole.read_stream("WordDocument").unwrap(), expected);
+        assert!(ole.sector_data_mut(100).is_err());
+    }
+
+    #[test]
+    fn remove_stream() {
+        let mut ole = open(&build(vec![
+            stream("A", pattern(10, 1)),
+            stream("B", pattern(20, 2)),
+            stream("C", pattern(30, 3)),
+            stream("D", pattern(40, 4)),
+            stream("E", pattern(50, 5)),
+        ]));
+        ole.remove_stream("B").unwrap();
+        ole.remove_stream("D").unwrap();
+        let names = ole.iter_streams().map(|(path, _)| path).collect::<Vec<String>>();
+        assert_eq!(names, vec!["/A", "/C", "/E"]);
+        assert_eq!(ole.free_directory_slots(), vec![2, 4, 6, 7]);
+        assert!(ole.remove_stream("B").is_err());
+    }
+
+    #[test]
+    fn merge_at() {
+        let mut ole = open(&build(vec![
+            stream("WordDocument", pattern(6144, 7)),
+            storage("ObjectPool", vec![]),
+        ]));
+        let other = open(&build(vec![
+            stream("Contents", pattern(5000, 1)),
+            storage("Nested", vec![stream("\u{1}Ole", pattern(20, 2))]),
+        ]));
+        ole.merge_at("ObjectPool", &other).unwrap();
+
+        assert_eq!(ole.read_stream("WordDocument").unwrap(), pattern(6144, 7));
+        assert_eq!(ole.read_stream("ObjectPool/Contents").unwrap(), pattern(5000, 1));
+        let small = "ObjectPool/Nested/\u{1}Ole".locate(&ole).unwrap();
+        assert_eq!(ole.read_cow(small).unwrap().to_vec(), pattern(20, 2));
+        assert_eq!(ole.verify().unwrap(), vec![]);
+        assert!(matches!(ole.merge_at("ObjectPool", &other), Err(OleError::EntryExists(_))));
+
+        // a source stream whose chain ends early fails the merge instead of arriving short
+        let mut broken = open(&build(vec![stream("Contents", pattern(5000, 1)), stream("Empty", vec![])]));
+        broken.entries.as_mut().unwrap()[1].chain.as_mut().unwrap().truncate(3);
+        let mut ole = open(&build(vec![storage("ObjectPool", vec![])]));
+        assert!(ole.merge_at("ObjectPool", &broken).is_err());
+        assert!(ole.read_stream("ObjectPool/Contents").is_err());
+
+        let mut ole = open(&build(vec![storage("ObjectPool", vec![])]));
+        ole.merge_at("ObjectPool", &open(&build(vec![stream("Empty", vec![])]))).unwrap();
+        assert_eq!(ole.resolve_path("ObjectPool/Empty").unwrap().stream_size, 0);
+    }
+
+    #[test]
+    fn shrink_to_fit() {
+        let mut ole = open(&build(vec![
+            stream("WordDocument", pattern(6144, 7)),
+            stream("Last", pattern(8192, 1)),
+        ]));
+        let before = ole.body.len();
+        ole.remove_stream("Last").unwrap();
+        assert_eq!(ole.body.len(), before);
+
+        ole.shrink_to_fit().unwrap();
+        assert_eq!(ole.body.len(), before - 16);
+        assert_eq!(ole.fat.as_ref().unwrap().len(), 128);
+        assert_eq!(ole.verify().unwrap(), vec![]);
+        assert_eq!(ole.read_stream("WordDocument").unwrap(), pattern(6144, 7));
+
+        // with the stream that needed it gone, the DIFAT sector goes too
+        let mut ole = open(&build(vec![stream("WordDocument", pattern(6144, 7))]));
+        let before = ole.body.len();
+        ole.write_stream("Large", &pattern(14000 * 512, 3)).unwrap();
+        assert_eq!(ole.header.number_of_difat_sectors, 1);
+        ole.remove_stream("Large").unwrap();
+        ole.shrink_to_fit().unwrap();
+        assert_eq!(ole.header.number_of_difat_sectors, 0);
+        assert_eq!(ole.header.first_difat_sector_location, SectorType::EndOfChain);
+        assert_eq!(ole.body.len(), before);
+        assert_eq!(ole.header.number_of_fat_sectors as usize, ole.difat.len());
+        let saved = open(&ole.to_bytes().unwrap());
+        assert_eq!(saved.verify().unwrap(), vec![]);
+        assert_eq!(saved.read_stream("WordDocument").unwrap(), pattern(6144, 7));
+    }
+
+    #[test]
+    fn removals_keep_sibling_trees_balanced() {
+        let mut ole = open(&build(vec![stream("WordDocument", pattern(6144, 7))]));
+        for i in 0..20 {
+            ole.insert_stream(&format!("S{:02}", i), &pattern(10, i as u8)).unwrap();
+        }
+        for i in (0..20).step_by(3) {
+            ole.remove_stream(&format!("S{:02}", i)).unwrap();
+            let entries = ole.entries.as_ref().unwrap();
+            let root = &entries[0].child_id;
+            if let SectorType::RegularSect(v) = root {
+                assert_eq!(entries[*v as usize].color, Color::Black);
+            }
+            assert!(black_height(entries, root).is_some());
+        }
+        for i in 0..20 {
+            assert_eq!(ole.read_stream(format!("S{:02}", i).as_str()).is_ok(), i % 3 != 0);
+        }
+        assert_eq!(ole.verify().unwrap(), vec![]);
+    }
+
+    #[test]
+    fn write_stream_from_reader_rejects_missing_parent() {
+        let mut ole = open(&build(vec![stream("WordDocument", pattern(6144, 7))]));
+        assert!(ole.write_stream_from_reader("Missing/Data", &mut Cursor::new(vec![1, 2, 3])).is_err());
+    }
+
+    #[test]
+    fn insert_into_file_without_mini_fat() {
+        // the 100-byte stream sits in the regular FAT, there is no mini FAT
+        let mut bytes = build_with_cutoff(64, vec![
+            stream("WordDocument", pattern(6144, 7)),
+            stream("Small", pattern(100, 1)),
+        ]);
+        bytes[0x38..0x3C].copy_from_slice(&4096u32.to_le_bytes());
+        let mut ole = open(&bytes);
+        assert!(ole.mini_fat.is_none());
+        assert_eq!(ole.read_stream("Small").unwrap(), pattern(100, 1));
+
+        ole.insert_stream("New", &[9; 50]).unwrap();
+        assert!(ole.mini_fat.is_some());
+        assert_eq!(ole.read_stream("Small").unwrap(), pattern(100, 1));
+        assert_eq!(ole.read_stream("New").unwrap(), vec![9; 50]);
+        assert_eq!(ole.verify().unwrap(), vec![]);
+        assert!(ole.orphaned_sectors().unwrap().is_empty());
+
+        let saved = open(&ole.to_bytes().unwrap());
+        assert_eq!(saved.read_stream("Small").unwrap(), pattern(100, 1));
+        assert_eq!(saved.read_stream("New").unwrap(), vec![9; 50]);
+        assert_eq!(saved.read_stream("WordDocument").unwrap(), pattern(6144, 7));
+
+        // rewriting the small stream itself frees its regular sectors
+        let mut ole = open(&bytes);
+        ole.write_stream("Small", &pattern(80, 2)).unwrap();
+        assert_eq!(ole.read_stream("Small").unwrap(), pattern(80, 2));
+        assert!(ole.orphaned_sectors().unwrap().is_empty());
+        assert_eq!(ole.verify().unwrap(), vec![]);
+    }
+
+    #[test]
+    fn write_stream_from_reader_keeps_old_contents_on_read_error() {
+        let mut ole = open(&build(vec![stream("WordDocument", pattern(6144, 7))]));
+        let used = |ole: &Ole| ole.fat.as_ref().unwrap().iter().filter(|v| **v != SectorType::FreeSect).count();
+        let before = used(&ole);
+        let mut reader = Cursor::new(pattern(5000, 9)).chain(FailingReader);
+        let err = ole.write_stream_from_reader("WordDocument", &mut reader).unwrap_err();
+        assert!(matches!(err, OleError::IoError(_)));
+        assert_eq!(ole.read_stream("WordDocument").unwrap(), pattern(6144, 7));
+        assert_eq!(used(&ole), before);
+        assert!(ole.orphaned_sectors().unwrap().is_empty());
+        assert_eq!(open(&ole.to_bytes().unwrap()).read_stream("WordDocument").unwrap(), pattern(6144, 7));
+    }
+
+    #[test]
+    fn write_stream_from_reader_bounds_lookahead() {
+        // a header cutoff of u32::MAX makes every stream a mini stream, read mini
+        // sector by mini sector rather than buffered whole
+        let mut ole = open(&build(vec![stream("WordDocument", pattern(6144, 7))]));
+        ole.header.mini_stream_cutoff_size = u32::MAX;
+        let mut reader = CountingReader { inner: Cursor::new(pattern(40000, 9)), largest: 0 };
+        ole.write_stream_from_reader("Data", &mut reader).unwrap();
+        assert!(reader.largest <= SPEC_MINI_STREAM_CUTOFF as usize);
+        assert!(ole.in_mini_stream(ole.resolve_path("Data").unwrap()));
+        assert_eq!(ole.read_stream("Data").unwrap(), pattern(40000, 9));
+    }
+
+    #[test]
+    fn write_stream_from_reader_past_large_header_cutoff() {
+        // past the lookahead the stream starts out mini and moves to regular sectors
+        // on reaching the header's cutoff
+        let mut ole = open(&build_with_cutoff(8192, vec![stream("WordDocument", pattern(6144, 7))]));
+        let used = |ole: &Ole| ole.mini_fat.as_ref().unwrap().iter().filter(|v| **v != SectorType::FreeSect).count();
+        let before = used(&ole);
+        let mut reader = CountingReader { inner: Cursor::new(pattern(10000, 9)), largest: 0 };
+        ole.write_stream_from_reader("Data", &mut reader).unwrap();
+        assert!(reader.largest <= SPEC_MINI_STREAM_CUTOFF as usize);
+        assert!(!ole.in_mini_stream(ole.resolve_path("Data").unwrap()));
+        assert_eq!(ole.read_stream("Data").unwrap(), pattern(10000, 9));
+        assert_eq!(used(&ole), before);
+        assert!(ole.orphaned_sectors().unwrap().is_empty());
+
+        ole.write_stream_from_reader("Small", &mut Cursor::new(pattern(6000, 3))).unwrap();
+        assert!(ole.in_mini_stream(ole.resolve_path("Small").unwrap()));
+        assert_eq!(open(&ole.to_bytes().unwrap()).read_stream("Small").unwrap(), pattern(6000, 3));
+    }
+
+    #[test]
+    fn write_stream_from_reader_leaves_no_entry_on_read_error() {
+        for len in [100, 5000] {
+            let mut ole = open(&build(vec![stream("WordDocument", pattern(6144, 7))]));
+            let mut reader = Cursor::new(pattern(len, 9)).chain(FailingReader);
+            let err = ole.write_stream_from_reader("New", &mut reader).unwrap_err();
+            assert!(matches!(err, OleError::IoError(_)));
+            assert!(matches!(ole.resolve_path("New"), Err(OleError::PathNotFound(_))));
+            assert!(ole.orphaned_sectors().unwrap().is_empty());
+            assert_eq!(ole.verify().unwrap(), vec![]);
+        }
+    }
+
+    #[test]
+    fn allocate_sector_skips_past_hint() {
+        let mut ole = open(&build(vec![stream("WordDocument", pattern(6144, 7))]));
+        ole.remove_stream("WordDocument").unwrap();
+        let first = ole.allocate_sector().unwrap();
+        assert_eq!(ole.free_hint, first as usize + 1);
+        let second = ole.allocate_sector().unwrap();
+        assert!(second > first);
+        assert_eq!(ole.free_hint, second as usize + 1);
+    }
+
+    struct CountingReader {
+        inner: Cursor<Vec<u8>>,
+        largest: usize,
+    }
+
+    impl Read for CountingReader {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            self.largest = std::cmp::max(self.largest, buf.len());
+            self.inner.read(buf)
+        }
+    }
+
+    struct FailingReader;
+
+    impl Read for FailingReader {
+        fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+            Err(std::io::ErrorKind::BrokenPipe.into())
+        }
+    }
+}
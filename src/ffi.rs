@@ -0,0 +1,208 @@
+//! C ABI over the read path, declared in `include/rust_ole.h`
+//!
+//! a file is an opaque `OleHandle` pointer from `ole_open`, released with
+//! `ole_free`; streams are addressed by their position in `Ole::stream_descriptors`,
+//! listed once when the file is opened, and everything but `ole_open` returns one of
+//! the `OLE_*` codes below; a panic inside any call comes back as `OLE_ERR_OTHER`
+//! (a null handle from `ole_open`) instead of unwinding into C
+//!
+//! the library is only built as an rlib by default, the shared library comes from
+//! `cargo rustc --release --features ffi --crate-type cdylib`
+use crate::common::OleError;
+use crate::ole::Ole;
+use crate::tree::StreamDescriptor;
+use std::ffi::{c_char, c_int, CStr};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+
+pub const OLE_OK: c_int = 0;
+pub const OLE_ERR_NULL_POINTER: c_int = 1;
+pub const OLE_ERR_OUT_OF_RANGE: c_int = 2;
+/// the buffer is too small, the required length was written to the length
+pub const OLE_ERR_BUFFER_TOO_SMALL: c_int = 3;
+pub const OLE_ERR_IO: c_int = 4;
+pub const OLE_ERR_INVALID_FORMAT: c_int = 5;
+pub const OLE_ERR_INVALID_ENTRY: c_int = 6;
+pub const OLE_ERR_NOT_FOUND: c_int = 7;
+pub const OLE_ERR_OTHER: c_int = 8;
+
+fn error_code(err: &OleError) -> c_int {
+    match err {
+        OleError::IoError(_) => OLE_ERR_IO,
+        OleError::InvalidFileFormat
+        | OleError::InvalidDifat
+        | OleError::ParseError(_)
+        | OleError::InvalidRootEntry
+        | OleError::FatCountMismatch { .. }
+        | OleError::DifatCountMismatch { .. }
+        | OleError::TruncatedStream { .. } => OLE_ERR_INVALID_FORMAT,
+        OleError::InvalidEntryIndex
+        | OleError::InvalidEntrySize
+        | OleError::InvalidEntryChain
+        | OleError::InvalidEntryName(_)
+        | OleError::NoChain { .. } => OLE_ERR_INVALID_ENTRY,
+        OleError::PathNotFound(_) | OleError::NotAStream(_) | OleError::NotAStorage(_) => OLE_ERR_NOT_FOUND,
+        _ => OLE_ERR_OTHER,
+    }
+}
+
+/// copy `data` into the caller's buffer of `*len` bytes, always reporting the
+/// full length back through `len`
+unsafe fn copy_out(data: &[u8], out: *mut u8, len: *mut usize) -> c_int {
+    let capacity = *len;
+    *len = data.len();
+    if capacity < data.len() {
+        return OLE_ERR_BUFFER_TOO_SMALL;
+    }
+    if !data.is_empty() {
+        if out.is_null() {
+            return OLE_ERR_NULL_POINTER;
+        }
+        ptr::copy_nonoverlapping(data.as_ptr(), out, data.len());
+    }
+    OLE_OK
+}
+
+/// an open file with its streams listed
+pub struct OleHandle {
+    ole: Ole,
+    streams: Vec<StreamDescriptor>,
+}
+
+/// run a call's body, turning a panic into `OLE_ERR_OTHER`
+fn guarded(body: impl FnOnce() -> c_int) -> c_int {
+    panic::catch_unwind(AssertUnwindSafe(body)).unwrap_or(OLE_ERR_OTHER)
+}
+
+/// open and parse the file at the NUL-terminated UTF-8 `path`, null on any failure
+///
+/// # Safety
+/// `path` must be null or point to a NUL-terminated string
+#[no_mangle]
+pub unsafe extern "C" fn ole_open(path: *const c_char) -> *mut OleHandle {
+    if path.is_null() {
+        return ptr::null_mut();
+    }
+    let Ok(path) = CStr::from_ptr(path).to_str() else {
+        return ptr::null_mut();
+    };
+    let opened = panic::catch_unwind(|| {
+        let mut ole = Ole::open(path)?;
+        ole.parse()?;
+        let streams = ole.stream_descriptors();
+        Ok::<_, OleError>(OleHandle { ole, streams })
+    });
+    match opened {
+        Ok(Ok(handle)) => Box::into_raw(Box::new(handle)),
+        _ => ptr::null_mut(),
+    }
+}
+
+/// number of streams in the file
+///
+/// # Safety
+/// `ole` must come from `ole_open`, `count` must be writable
+#[no_mangle]
+pub unsafe extern "C" fn ole_stream_count(ole: *const OleHandle, count: *mut usize) -> c_int {
+    let (Some(ole), false) = (ole.as_ref(), count.is_null()) else {
+        return OLE_ERR_NULL_POINTER;
+    };
+    *count = ole.streams.len();
+    OLE_OK
+}
+
+/// full path of stream `index` as UTF-8 without a terminating NUL; `*len` holds the
+/// buffer size on entry and the path length on return
+///
+/// # Safety
+/// `ole` must come from `ole_open`, `len` must be writable and `out` must hold
+/// `*len` bytes
+#[no_mangle]
+pub unsafe extern "C" fn ole_stream_path(ole: *const OleHandle, index: usize, out: *mut u8, len: *mut usize) -> c_int {
+    let (Some(ole), false) = (ole.as_ref(), len.is_null()) else {
+        return OLE_ERR_NULL_POINTER;
+    };
+    match ole.streams.get(index) {
+        Some(descriptor) => copy_out(descriptor.path.as_bytes(), out, len),
+        None => OLE_ERR_OUT_OF_RANGE,
+    }
+}
+
+/// contents of stream `index`; `*len` holds the buffer size on entry and the
+/// stream size on return
+///
+/// # Safety
+/// `ole` must come from `ole_open`, `len` must be writable and `out` must hold
+/// `*len` bytes
+#[no_mangle]
+pub unsafe extern "C" fn ole_read_stream(ole: *const OleHandle, index: usize, out: *mut u8, len: *mut usize) -> c_int {
+    let (Some(ole), false) = (ole.as_ref(), len.is_null()) else {
+        return OLE_ERR_NULL_POINTER;
+    };
+    let Some(descriptor) = ole.streams.get(index) else {
+        return OLE_ERR_OUT_OF_RANGE;
+    };
+    guarded(|| match ole.ole.read_stream(descriptor.id as usize) {
+        Ok(data) => copy_out(&data, out, len),
+        Err(err) => error_code(&err),
+    })
+}
+
+/// release a file from `ole_open`, null is ignored
+///
+/// # Safety
+/// `ole` must come from `ole_open` and not be used afterwards
+#[no_mangle]
+pub unsafe extern "C" fn ole_free(ole: *mut OleHandle) {
+    if !ole.is_null() {
+        let _ = panic::catch_unwind(AssertUnwindSafe(|| drop(Box::from_raw(ole))));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CString;
+
+    #[test]
+    fn open_read_free() {
+        let path = CString::new("./abcd.doc").unwrap();
+        unsafe {
+            let ole = ole_open(path.as_ptr());
+            assert!(!ole.is_null());
+
+            let mut count = 0;
+            assert_eq!(ole_stream_count(ole, &mut count), OLE_OK);
+            assert_eq!(count, (*ole).ole.iter_streams().count());
+
+            let index = (*ole).streams.iter().position(|v| v.path == "/WordDocument").unwrap();
+            let mut buf = [0u8; 64];
+            let mut len = buf.len();
+            assert_eq!(ole_stream_path(ole, index, buf.as_mut_ptr(), &mut len), OLE_OK);
+            assert_eq!(&buf[..len], b"/WordDocument");
+
+            let mut len = 0;
+            assert_eq!(ole_read_stream(ole, index, ptr::null_mut(), &mut len), OLE_ERR_BUFFER_TOO_SMALL);
+            assert_eq!(len, 6144);
+            let mut data = vec![0u8; len];
+            assert_eq!(ole_read_stream(ole, index, data.as_mut_ptr(), &mut len), OLE_OK);
+            assert_eq!(data, (*ole).ole.read_stream("WordDocument").unwrap());
+
+            assert_eq!(ole_read_stream(ole, count, data.as_mut_ptr(), &mut len), OLE_ERR_OUT_OF_RANGE);
+            ole_free(ole);
+        }
+
+        let missing = CString::new("./missing.doc").unwrap();
+        unsafe {
+            assert!(ole_open(missing.as_ptr()).is_null());
+            assert_eq!(ole_stream_count(ptr::null(), &mut 0), OLE_ERR_NULL_POINTER);
+            ole_free(ptr::null_mut());
+        }
+    }
+
+    #[test]
+    fn panics_become_error_codes() {
+        assert_eq!(guarded(|| OLE_OK), OLE_OK);
+        assert_eq!(guarded(|| panic!("in a call")), OLE_ERR_OTHER);
+    }
+}
@@ -0,0 +1,58 @@
+use crate::common::OleResult;
+use crate::directory::ObjectType;
+use crate::ole::{Ole, StreamLocator};
+use sha2::{Digest, Sha256};
+
+impl Ole {
+    /// SHA-256 of a stream's contents, fed sector by sector instead of reading it whole
+    pub fn hash_stream<'a, L: StreamLocator<'a>>(&'a self, loc: L) -> OleResult<[u8; 32]> {
+        let mut hasher = Sha256::new();
+        self.for_each_sector(loc.locate(self)?, |v| hasher.update(v))?;
+        Ok(hasher.finalize().into())
+    }
+
+    /// path and SHA-256 of every stream, in tree order
+    pub fn hash_manifest(&self) -> OleResult<Vec<(String, [u8; 32])>> {
+        let entries = self.entries_ref()?;
+        let mut manifest = vec![];
+        for (path, idx) in self.entry_paths()? {
+            let entry = &entries[idx];
+            if entry.object_type == ObjectType::Stream {
+                manifest.push((path, self.hash_stream(entry)?));
+            }
+        }
+        Ok(manifest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_util::*;
+    use sha2::{Digest, Sha256};
+
+    #[test]
+    fn hash_stream() {
+        let ole = open(&build(vec![stream("Abc", b"abc".to_vec()), stream("Large", pattern(6144, 7))]));
+        let hex = |digest: [u8; 32]| digest.iter().map(|v| format!("{:02x}", v)).collect::<String>();
+        assert_eq!(hex(ole.hash_stream("Abc").unwrap()), "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+        assert_eq!(ole.hash_stream("Large").unwrap(), <[u8; 32]>::from(Sha256::digest(pattern(6144, 7))));
+    }
+
+    #[test]
+    fn hash_manifest() {
+        let first = open(&build(vec![
+            stream("WordDocument", pattern(6144, 7)),
+            stream("Small", pattern(100, 1)),
+        ]));
+        let second = open(&build(vec![
+            storage("Nested", vec![stream("WordDocument", pattern(6144, 7))]),
+            stream("Small", pattern(100, 2)),
+        ]));
+        let a = first.hash_manifest().unwrap();
+        let b = second.hash_manifest().unwrap();
+        assert_eq!(a.iter().map(|(path, _)| path.as_str()).collect::<Vec<&str>>(), vec!["/Small", "/WordDocument"]);
+        assert_eq!(a[1].1, b[1].1);
+        assert_eq!(b[1].0, "/Nested/WordDocument");
+        assert_ne!(a[0].1, b[0].1);
+    }
+}
@@ -1,24 +1,24 @@
-use crate::common::{MajorVersion, MinorVersion, SectorShift, SectorType};
-use crate::fat::Fat;
-use binrw::{binrw, BinRead, BinWrite};
-use std::cmp::PartialEq;
+use crate::common::{MajorVersion, SectorType};
+use binrw::binrw;
 use std::fmt::{Display, Formatter};
-use crate::difat::{AllEntryDifat, Difat};
 
 #[binrw]
 #[brw(little)]
 #[brw(magic(0xE11AB1A1E011CFD0u64))]
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Header {
     // Header Signature (8 bytes): Identification signature for the compound file structure, and MUST be set to the value 0xD0, 0xCF, 0x11, 0xE0, 0xA1, 0xB1, 0x1A, 0xE1.
     // pub signature: [u8; 8],
 
     // Header CLSID (16 bytes): Reserved and unused class ID that MUST be set to all zeroes (CLSID_NULL).
-    // pub clsid: [u8; 16],
+    // Some writers leave stray bytes here, so it's read as-is and checked with `header_clsid_is_zero`.
+    #[cfg_attr(feature = "serde", serde(serialize_with = "crate::metadata::clsid"))]
+    pub header_clsid: [u8; 16],
 
     // Minor Version (2 bytes): Version number for nonbreaking changes. This field SHOULD be set to 0x003E if the major version field is either 0x0003 or 0x0004.
-    #[brw(magic(0x0000u128))]
-    pub minor_version: MinorVersion,
+    // Kept raw, writers do use other values and they have to survive a round trip.
+    pub minor_version: u16,
 
     //Major Version (2 bytes): Version number for breaking changes. This field MUST be set to either 0x0003 (version 3) or 0x0004 (version 4).
     pub major_version: MajorVersion,
@@ -38,12 +38,12 @@ pub struct Header {
     pub mini_sector_shift: u16,
 
     // Reserved (6 bytes): This field MUST be set to all zeroes.
-    // pub reserved: [u8; 6],
+    // Read as-is like the header CLSID, see `reserved_is_zero`.
+    pub reserved: [u8; 6],
 
     //Number of Directory Sectors (4 bytes): This integer field contains the count of the number of directory sectors in the compound file.
     //
     // If Major Version is 3, the Number of Directory Sectors MUST be zero. This field is not supported for version 3 compound files.
-    #[brw(magic(b"\0\0\0\0\0\0"))]
     pub number_of_directory_sectors: u32,
 
     //Number of FAT Sectors (4 bytes): This integer field contains the count of the number of FAT sectors in the compound file.
@@ -74,4 +74,92 @@ impl Display for Header{
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(f, "version: {:?}", &self.major_version)
     }
-}
\ No newline at end of file
+}
+
+impl Header {
+    /// the header CLSID MUST be CLSID_NULL
+    pub fn header_clsid_is_zero(&self) -> bool {
+        self.header_clsid.iter().all(|v| *v == 0)
+    }
+
+    /// the 6 reserved bytes MUST be zero
+    pub fn reserved_is_zero(&self) -> bool {
+        self.reserved.iter().all(|v| *v == 0)
+    }
+
+    /// sector size the file was written with, going by `sector_shift` rather than
+    /// the major version, which some writers get wrong
+    pub fn sector_size(&self) -> usize {
+        1 << self.sector_shift
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::{MajorVersion, ParseWarning};
+    use crate::ole::Ole;
+    use crate::test_util::*;
+    use binrw::BinWrite;
+    use std::io::Cursor;
+
+    #[test]
+    fn nonzero_reserved_fields_warn() {
+        let mut bytes = build(vec![stream("WordDocument", pattern(6144, 7))]);
+        bytes[0x08] = 0x01;
+        bytes[0x24] = 0x7F;
+        let ole = open(&bytes);
+        assert!(!ole.header.header_clsid_is_zero());
+        assert!(!ole.header.reserved_is_zero());
+        assert_eq!(ole.warnings, vec![ParseWarning::NonZeroHeaderClsid, ParseWarning::NonZeroReserved]);
+        assert_eq!(ole.read_stream(1).unwrap(), pattern(6144, 7));
+
+        let ole = open(&build(vec![]));
+        assert!(ole.header.header_clsid_is_zero() && ole.header.reserved_is_zero());
+        assert!(ole.warnings.is_empty());
+    }
+
+    #[test]
+    fn version3_directory_sector_count_warns() {
+        let mut bytes = build(vec![stream("WordDocument", pattern(6144, 7))]);
+        bytes[0x28] = 3;
+        let ole = open(&bytes);
+        assert_eq!(ole.header.number_of_directory_sectors, 3);
+        assert_eq!(ole.warnings, vec![ParseWarning::NonZeroDirectorySectorCount(3)]);
+        assert_eq!(ole.read_stream("WordDocument").unwrap(), pattern(6144, 7));
+    }
+
+    #[test]
+    fn minor_version_round_trips() {
+        let mut bytes = build(vec![stream("WordDocument", pattern(6144, 7))]);
+        bytes[0x18..0x1A].copy_from_slice(&0x0040u16.to_le_bytes());
+        let mut ole = open(&bytes);
+        assert_eq!(ole.header.minor_version, 0x0040);
+
+        let mut out = Cursor::new(vec![]);
+        ole.header.write_le(&mut out).unwrap();
+        assert_eq!(out.get_ref()[..], bytes[..76]);
+
+        ole.set_minor_version(0x003E);
+        let mut out = Cursor::new(vec![]);
+        ole.header.write_le(&mut out).unwrap();
+        assert_eq!(out.get_ref()[0x18..0x1A], [0x3E, 0x00]);
+        assert_eq!(out.get_ref()[0x1A..], bytes[0x1A..76]);
+    }
+
+    #[test]
+    fn sector_size_follows_shift() {
+        assert_eq!(open_unparsed(&build(vec![])).header.sector_size(), 512);
+
+        // a version 3 label with version 4 sectors
+        let mut bytes = build(vec![]);
+        bytes[0x1E..0x20].copy_from_slice(&12u16.to_le_bytes());
+        bytes.resize(8192, 0);
+        let ole = open_unparsed(&bytes);
+        assert_eq!(ole.version, MajorVersion::Version3);
+        assert_eq!(ole.header.sector_size(), 4096);
+        assert_eq!(ole.body[0].len(), 4096);
+
+        bytes[0x1E..0x20].copy_from_slice(&10u16.to_le_bytes());
+        assert!(Ole::from_slice(&bytes).is_err());
+    }
+}
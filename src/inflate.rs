@@ -0,0 +1,124 @@
+use crate::common::{OleError, OleResult};
+use crate::ole::{Ole, StreamLocator};
+use flate2::bufread::{DeflateDecoder, ZlibDecoder};
+use std::io::{ErrorKind, Read};
+
+/// how a stream's payload was deflated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompressionFormat {
+    /// RFC 1950: a two byte header, deflate data and an Adler-32 trailer
+    Zlib,
+    /// RFC 1951 deflate data with no framing
+    Deflate,
+}
+
+impl Ole {
+    /// read a stream and inflate its contents
+    pub fn read_stream_inflate<'a, L: StreamLocator<'a>>(&'a self, loc: L, format: CompressionFormat) -> OleResult<Vec<u8>> {
+        self.read_stream_inflate_limited(loc, format, usize::MAX)
+    }
+
+    /// `read_stream_inflate` for untrusted input: inflating stops with
+    /// `StreamTooLarge` once the output passes `max_bytes`, so a small stream can't
+    /// expand without bound
+    pub fn read_stream_inflate_limited<'a, L: StreamLocator<'a>>(&'a self, loc: L, format: CompressionFormat, max_bytes: usize) -> OleResult<Vec<u8>> {
+        let reader = self.open_stream(loc)?;
+        let mut decoder: Box<dyn Read> = match format {
+            CompressionFormat::Zlib => Box::new(ZlibDecoder::new(reader)),
+            CompressionFormat::Deflate => Box::new(DeflateDecoder::new(reader)),
+        };
+        let mut out = vec![];
+        decoder.by_ref().take(max_bytes as u64).read_to_end(&mut out).map_err(decode_error)?;
+        if out.len() == max_bytes && decoder.read(&mut [0]).map_err(decode_error)? > 0 {
+            return Err(OleError::StreamTooLarge { size: max_bytes as u64 + 1, limit: max_bytes });
+        }
+        Ok(out)
+    }
+}
+
+/// corrupt compressed data is a format error, anything else stays an I/O error
+fn decode_error(err: std::io::Error) -> OleError {
+    match err.kind() {
+        ErrorKind::InvalidInput | ErrorKind::InvalidData => OleError::InvalidFileFormat,
+        _ => err.into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CompressionFormat;
+    use crate::common::OleError;
+    use crate::test_util::*;
+
+    fn source() -> Vec<u8> {
+        (0..40).flat_map(|i| format!("line {}: the quick brown fox jumps over the lazy dog\n", i).into_bytes()).collect()
+    }
+
+    // zlib.compress(source(), 9)
+    const ZLIB: [u8; 167] = [
+        0x78, 0xDA, 0x9D, 0xD5, 0x5B, 0x16, 0xC1, 0x50, 0x0C, 0x46, 0xE1, 0x77, 0xA3, 0xC8, 0x10, 0xE4,
+        0x0F, 0x2D, 0x66, 0xE3, 0x72, 0x68, 0x39, 0x7A, 0x68, 0xD5, 0x6D, 0xF4, 0x16, 0x33, 0xB0, 0x9F,
+        0xB3, 0xF6, 0x53, 0xBE, 0x95, 0xE4, 0xB6, 0x4B, 0x36, 0x5D, 0xD9, 0xAD, 0x49, 0x76, 0x1D, 0xDB,
+        0xED, 0xC9, 0x36, 0x7D, 0x79, 0x74, 0xB6, 0x2F, 0x4F, 0x3B, 0x8E, 0xE7, 0xCB, 0x60, 0xE5, 0x9E,
+        0xFA, 0xDF, 0x38, 0xAF, 0xDF, 0x2F, 0xDB, 0x95, 0xC3, 0x24, 0x7F, 0x1B, 0x07, 0x8D, 0x40, 0x13,
+        0xA0, 0x99, 0x81, 0x66, 0x0E, 0x9A, 0x0A, 0x34, 0x35, 0x68, 0x16, 0xA0, 0x59, 0x92, 0x9D, 0x22,
+        0x08, 0x44, 0x82, 0x13, 0x0A, 0x4E, 0x2C, 0x38, 0xC1, 0xE0, 0x44, 0x83, 0x13, 0x0E, 0x4E, 0x3C,
+        0x38, 0x01, 0xE1, 0x44, 0x84, 0x88, 0x08, 0xA1, 0xDB, 0x40, 0x44, 0x88, 0x88, 0x10, 0x11, 0x21,
+        0x22, 0x42, 0x44, 0x84, 0x88, 0x08, 0x11, 0x11, 0x22, 0x22, 0x82, 0x88, 0x08, 0x22, 0x22, 0xD0,
+        0xBB, 0x20, 0x22, 0x82, 0x88, 0x08, 0x22, 0x22, 0x88, 0x88, 0x20, 0x22, 0x82, 0x88, 0x88, 0x3F,
+        0x45, 0x7C, 0x00, 0xD5, 0x99, 0xE3, 0xF7,
+    ];
+
+    // the same with wbits=-15, raw deflate
+    const DEFLATE: [u8; 161] = [
+        0x9D, 0xD5, 0x5B, 0x16, 0xC1, 0x50, 0x0C, 0x46, 0xE1, 0x77, 0xA3, 0xC8, 0x10, 0xE4, 0x0F, 0x2D,
+        0x66, 0xE3, 0x72, 0x68, 0x39, 0x7A, 0x68, 0xD5, 0x6D, 0xF4, 0x16, 0x33, 0xB0, 0x9F, 0xB3, 0xF6,
+        0x53, 0xBE, 0x95, 0xE4, 0xB6, 0x4B, 0x36, 0x5D, 0xD9, 0xAD, 0x49, 0x76, 0x1D, 0xDB, 0xED, 0xC9,
+        0x36, 0x7D, 0x79, 0x74, 0xB6, 0x2F, 0x4F, 0x3B, 0x8E, 0xE7, 0xCB, 0x60, 0xE5, 0x9E, 0xFA, 0xDF,
+        0x38, 0xAF, 0xDF, 0x2F, 0xDB, 0x95, 0xC3, 0x24, 0x7F, 0x1B, 0x07, 0x8D, 0x40, 0x13, 0xA0, 0x99,
+        0x81, 0x66, 0x0E, 0x9A, 0x0A, 0x34, 0x35, 0x68, 0x16, 0xA0, 0x59, 0x92, 0x9D, 0x22, 0x08, 0x44,
+        0x82, 0x13, 0x0A, 0x4E, 0x2C, 0x38, 0xC1, 0xE0, 0x44, 0x83, 0x13, 0x0E, 0x4E, 0x3C, 0x38, 0x01,
+        0xE1, 0x44, 0x84, 0x88, 0x08, 0xA1, 0xDB, 0x40, 0x44, 0x88, 0x88, 0x10, 0x11, 0x21, 0x22, 0x42,
+        0x44, 0x84, 0x88, 0x08, 0x11, 0x11, 0x22, 0x22, 0x82, 0x88, 0x08, 0x22, 0x22, 0xD0, 0xBB, 0x20,
+        0x22, 0x82, 0x88, 0x08, 0x22, 0x22, 0x88, 0x88, 0x20, 0x22, 0x82, 0x88, 0x88, 0x3F, 0x45, 0x7C,
+        0x00,
+    ];
+
+    #[test]
+    fn read_stream_inflate() {
+        let ole = open(&build(vec![
+            stream("Zlib", ZLIB.to_vec()),
+            stream("Deflate", DEFLATE.to_vec()),
+        ]));
+        assert_eq!(ole.read_stream_inflate("Zlib", CompressionFormat::Zlib).unwrap(), source());
+        assert_eq!(ole.read_stream_inflate("Deflate", CompressionFormat::Deflate).unwrap(), source());
+        assert!(ole.read_stream_inflate("Deflate", CompressionFormat::Zlib).is_err());
+    }
+
+    #[test]
+    fn inflate_stored_and_fixed_blocks() {
+        let stored = [0x78, 0x01, 0x01, 0x0C, 0x00, 0xF3, 0xFF, 0x73, 0x74, 0x6F, 0x72, 0x65, 0x64, 0x20, 0x62, 0x6C, 0x6F, 0x63, 0x6B, 0x1F, 0x80, 0x04, 0xBD];
+        let fixed = [0x78, 0xDA, 0x4B, 0x4C, 0x4A, 0x4E, 0x84, 0x21, 0x00, 0x1D, 0xE0, 0x04, 0x99];
+        let mut corrupt = fixed;
+        corrupt[12] ^= 1;
+        let ole = open(&build(vec![
+            stream("Stored", stored.to_vec()),
+            stream("Fixed", fixed.to_vec()),
+            stream("Corrupt", corrupt.to_vec()),
+        ]));
+        assert_eq!(ole.read_stream_inflate("Stored", CompressionFormat::Zlib).unwrap(), b"stored block");
+        assert_eq!(ole.read_stream_inflate("Fixed", CompressionFormat::Zlib).unwrap(), b"abcabcabcabc");
+        assert!(matches!(ole.read_stream_inflate("Corrupt", CompressionFormat::Zlib), Err(OleError::InvalidFileFormat)));
+    }
+
+    #[test]
+    fn read_stream_inflate_limited() {
+        let ole = open(&build(vec![stream("Zlib", ZLIB.to_vec())]));
+        let len = source().len();
+        assert_eq!(ole.read_stream_inflate_limited("Zlib", CompressionFormat::Zlib, len).unwrap(), source());
+        assert!(matches!(
+            ole.read_stream_inflate_limited("Zlib", CompressionFormat::Zlib, len - 1),
+            Err(OleError::StreamTooLarge { limit, .. }) if limit == len - 1
+        ));
+    }
+}
@@ -7,17 +7,49 @@ pub mod range_lock;
 pub mod common;
 pub mod header;
 pub mod ole;
+mod edit;
+pub mod verify;
+pub mod tree;
+pub mod stream;
+pub mod vba;
+pub mod classify;
+pub mod writer;
+pub mod reader;
+#[cfg(feature = "sha2")]
+mod hash;
+#[cfg(feature = "flate2")]
+pub mod inflate;
+#[cfg(feature = "serde")]
+pub mod metadata;
+#[cfg(feature = "ffi")]
+pub mod ffi;
 
 #[cfg(test)]
-mod tests {
-    use binrw::BinRead;
+mod test_util;
 
+#[cfg(test)]
+mod tests {
     #[test]
     fn it_works() {
         let mut h = crate::ole::Ole::from_path("./abcd.doc").unwrap();
         h.parse().unwrap();
         let entry = &h.entries.as_ref().unwrap()[1];
         let data = h.read(entry).unwrap();
-        println!("{:?}", data)
+        assert_eq!(data.len() as u64, entry.stream_size);
+    }
+
+    #[test]
+    fn reads_version4() {
+        let v3 = crate::ole::Ole::open("./abcd.doc").unwrap();
+        let mut converted = crate::ole::Ole::open("./abcd.doc").unwrap();
+        converted.convert_version(crate::common::MajorVersion::Version4).unwrap();
+        let bytes = converted.to_bytes().unwrap();
+        assert_eq!(bytes.len(), 4096 * (1 + converted.body.len()));
+
+        let v4 = crate::ole::Ole::from_slice(&bytes).and_then(|mut v| v.parse().map(|_| v)).unwrap();
+        assert_eq!(v4.version, crate::common::MajorVersion::Version4);
+        assert_eq!(v4.body, converted.body);
+        assert_eq!(v4.read_all_streams().unwrap(), v3.read_all_streams().unwrap());
+        assert_eq!(v4.read_stream("WordDocument").unwrap().len(), 6144);
     }
 }
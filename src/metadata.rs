@@ -0,0 +1,128 @@
+//! the file's structure as JSON: header fields, the DIFAT, a count of the FAT's
+//! entries by kind and every directory entry with its name decoded and its chain
+//! as sector numbers, stream contents left out
+//!
+//! the `serialize_with` helpers below give the derived `Serialize` impls their
+//! readable forms
+use crate::common::SectorType;
+use crate::directory::{clsid_to_string, decode_name, filetime_iso8601, Entry, ObjectType};
+use crate::header::Header;
+use crate::ole::Ole;
+use serde::{Serialize, Serializer};
+
+/// a CLSID in its registry form
+pub(crate) fn clsid<S: Serializer>(clsid: &[u8; 16], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&clsid_to_string(clsid))
+}
+
+/// the raw name bytes decoded up to the terminating NUL
+pub(crate) fn name<S: Serializer>(name: &[u8; 64], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&decode_name(name))
+}
+
+/// a FILETIME as ISO-8601, `null` when not set
+pub(crate) fn filetime<S: Serializer>(filetime: &u64, serializer: S) -> Result<S::Ok, S::Error> {
+    filetime_iso8601(*filetime).serialize(serializer)
+}
+
+/// a chain as its sector numbers, `null` when there's none
+pub(crate) fn chain<S: Serializer>(chain: &Option<Vec<SectorType>>, serializer: S) -> Result<S::Ok, S::Error> {
+    chain.as_ref()
+        .map(|v| v.iter().map(|v| u32::from(v.clone())).collect::<Vec<u32>>())
+        .serialize(serializer)
+}
+
+/// FAT entries counted by kind, regular sectors in chains make up the rest of `total`
+#[derive(Serialize)]
+struct FatSummary {
+    total: usize,
+    free: usize,
+    end_of_chain: usize,
+    fat_sectors: usize,
+    difat_sectors: usize,
+}
+
+impl FatSummary {
+    fn new(fat: &[SectorType]) -> Self {
+        let count = |kind: SectorType| fat.iter().filter(|v| **v == kind).count();
+        Self {
+            total: fat.len(),
+            free: count(SectorType::FreeSect),
+            end_of_chain: count(SectorType::EndOfChain),
+            fat_sectors: count(SectorType::FatSect),
+            difat_sectors: count(SectorType::DifSect),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Metadata<'a> {
+    header: &'a Header,
+    difat: &'a [SectorType],
+    fat: Option<FatSummary>,
+    entries: Vec<&'a Entry>,
+}
+
+impl Ole {
+    /// the header, DIFAT, FAT summary and directory entries as one JSON object, for
+    /// diffing or feeding to other tools; unused directory slots are left out, the
+    /// summary is `null` and the entries empty when the file hasn't been parsed
+    pub fn to_metadata_json(&self) -> String {
+        let entries = self.entries_ref().into_iter().flatten()
+            .filter(|v| v.object_type != ObjectType::Unknown)
+            .collect::<Vec<&Entry>>();
+        let fat = self.fat_ref().ok().map(|v| FatSummary::new(v));
+        let metadata = Metadata { header: &self.header, difat: &self.difat, fat, entries };
+        // plain structs and strings, there's nothing that can fail to serialize
+        serde_json::to_string(&metadata).expect("metadata serializes to JSON")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::SectorType;
+    use crate::directory::{clsid_from_str, clsid_to_string, Entry, ObjectType};
+    use crate::test_util::*;
+
+    #[test]
+    fn scalar_values() {
+        assert_eq!(serde_json::to_string(&SectorType::RegularSect(7)).unwrap(), "{\"RegularSect\":7}");
+        assert_eq!(serde_json::to_string(&SectorType::EndOfChain).unwrap(), "\"EndOfChain\"");
+        assert_eq!(serde_json::to_string(&ObjectType::RootStorage).unwrap(), "\"RootStorage\"");
+        let clsid = clsid_from_str("{00020906-0000-0000-C000-000000000046}").unwrap();
+        assert_eq!(clsid_to_string(&clsid), "{00020906-0000-0000-C000-000000000046}");
+    }
+
+    #[test]
+    fn to_metadata_json() {
+        let ole = open(&build(vec![
+            stream("WordDocument", pattern(1024, 7)),
+            stream("\u{5}SummaryInformation", pattern(100, 1)),
+        ]));
+        let json = ole.to_metadata_json();
+        assert!(json.starts_with("{\"header\":{\"header_clsid\":\"{00000000-0000-0000-0000-000000000000}\""));
+        assert!(json.contains("\"major_version\":\"Version3\","));
+        assert!(json.contains("\"name\":\"Root Entry\",\"name_length\":22,\"object_type\":\"RootStorage\""));
+        assert!(json.contains("\"name\":\"\\u0005SummaryInformation\""));
+
+        // both streams are mini streams: one FAT sector of 128 entries covers itself,
+        // the directory, the mini FAT and 3 sectors of mini stream container, with a
+        // chain ending in each of the last three
+        assert!(json.contains("\"fat\":{\"total\":128,\"free\":122,\"end_of_chain\":3,\"fat_sectors\":1,\"difat_sectors\":0}"));
+        assert!(open_unparsed(&build(vec![])).to_metadata_json().contains("\"fat\":null"));
+
+        let entries = ole.entries.as_ref().unwrap();
+        let entry = &entries[ole.path_index("WordDocument").unwrap()];
+        let chain = entry.chain.as_ref().unwrap().iter().map(|v| v.to_string()).collect::<Vec<String>>();
+        assert!(json.contains(&format!("\"stream_size\":1024,\"chain\":[{}]", chain.join(","))));
+        let unused = entries.iter().filter(|v| v.object_type == ObjectType::Unknown).count();
+        assert!(unused > 0);
+        assert_eq!(json.matches("\"name\":").count(), entries.len() - unused);
+
+        let mut entry = Entry::new("Plain", ObjectType::Storage);
+        entry.creation_time = 116444736000000000;
+        let json = serde_json::to_string(&entry).unwrap();
+        assert!(json.contains("\"creation_time\":\"1970-01-01T00:00:00Z\",\"modified_time\":null"));
+        assert!(json.ends_with("\"chain\":null}"));
+    }
+}
@@ -3,8 +3,8 @@ use crate::common::SectorType;
 
 /// mini fat sector
 /// https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-cfb/c5d235f7-b73c-4ec5-bf8d-5c08306cd023
-
-pub const MINI_FAT_SECTOR_SIZE: u16 = 64;
+pub const MINI_SECTOR_SHIFT: u16 = 6;
+pub const MINI_FAT_SECTOR_SIZE: u16 = 1 << MINI_SECTOR_SHIFT;
 
 #[derive(Debug, Clone, BinRead, BinWrite)]
 #[brw(little)]
@@ -1,16 +1,66 @@
-use crate::common::{get_sector_size, get_valid_entries, MajorVersion, OleError, OleResult, SectorType};
+use crate::common::{get_valid_entries, MajorVersion, OleError, OleResult, ParseWarning, SectorType};
 use crate::difat::{AllEntryDifat, Difat};
-use crate::directory::{Directory, Entry, ObjectType};
+use crate::directory::{compare_names, Directory, Entry, ObjectType};
 use crate::fat::Fat;
 use crate::header::Header;
 use crate::mini_fat::MiniFat;
+use crate::reader::OleReader;
 use binrw::BinRead;
-use std::fmt::{Display, Formatter};
-use std::fs;
-use std::io::{Cursor, Read};
-use std::slice::SliceIndex;
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::fmt::{Debug, Display, Formatter};
+use std::path::Path;
+use std::sync::OnceLock;
+use std::io::{Cursor, Read, Seek, SeekFrom};
 
-#[derive(Debug, Clone)]
+/// the spec fixes the mini stream cutoff at 4096 bytes
+pub const SPEC_MINI_STREAM_CUTOFF: u32 = 0x1000;
+
+/// knobs controlling how a file is interpreted
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseOptions {
+    /// route streams to the mini stream by the header's `mini_stream_cutoff_size`
+    /// as-is, like most writers do, instead of the spec value of 4096
+    pub honor_header_cutoff: bool,
+    /// deepest storage nesting tree traversals will follow before giving up with
+    /// `NestingTooDeep`, the root being depth 0
+    pub max_depth: usize,
+    /// read streams only through the chain their size implies, without `read`
+    /// retrying the other FAT when that chain comes up short
+    pub strict: bool,
+    /// longest FAT or mini FAT chain to follow before failing with
+    /// `InvalidEntryChain`, on top of the table length that always bounds it
+    pub max_chain_len: usize,
+}
+
+/// storages nested deeper than this are almost certainly crafted
+pub const DEFAULT_MAX_DEPTH: usize = 64;
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self { honor_header_cutoff: true, max_depth: DEFAULT_MAX_DEPTH, strict: false, max_chain_len: usize::MAX }
+    }
+}
+
+/// the steps of `parse`, in the order they run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseStage {
+    Difat,
+    Fat,
+    MiniFat,
+    Directory,
+}
+
+/// a run of stream bytes inside one body sector
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Segment {
+    pub sector: usize,
+    pub start: usize,
+    pub len: usize,
+}
+
+#[derive(Clone)]
 pub struct Ole {
     pub header: Header,
     pub version: MajorVersion,
@@ -20,39 +70,198 @@ pub struct Ole {
     pub fat: Option<Vec<SectorType>>,
 
     pub entries: Option<Vec<Entry>>,
+    pub warnings: Vec<ParseWarning>,
+    /// sectors holding the mini FAT, in chain order
+    pub(crate) mini_fat_sectors: Vec<SectorType>,
+    pub options: ParseOptions,
+
+    pub(crate) body: Vec<Vec<u8>>,
+    /// entries were handed out mutably since the directory was last written into
+    /// `body`, `to_writer` serializes them afresh
+    pub(crate) directory_dirty: bool,
+    /// set by `open`: the tables below the DIFAT are parsed into the cell on first
+    /// use, and moved into the fields above by anything taking `&mut self`
+    pub(crate) deferred: Option<OnceLock<Tables>>,
+    /// no regular sector below this one is free, where `allocate_sector` starts looking
+    pub(crate) free_hint: usize,
+}
 
-    body: Vec<Vec<u8>>,
+/// everything parsed after the DIFAT, kept apart so `Ole::open` can fill it on the
+/// first read or listing
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Tables {
+    fat: Option<Vec<SectorType>>,
+    mini_fat: Option<Vec<SectorType>>,
+    mini_fat_sectors: Vec<SectorType>,
+    directory: Option<Vec<SectorType>>,
+    entries: Option<Vec<Entry>>,
+    warnings: Vec<ParseWarning>,
 }
 
 
 impl Display for Ole {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}\n", &self.header.to_string())?;
-        self.entries.as_ref().unwrap()
-            .iter()
-            .for_each(|v| {
-                write!(f, "{}\n", v.to_string()).unwrap();
-            });
+        writeln!(f, "{}", &self.header)?;
+        for entry in self.entries_ref().into_iter().flatten() {
+            writeln!(f, "{}", entry)?;
+        }
 
         Ok(())
     }
 }
+/// anything that identifies a stream: an entry, its index in the directory, or its path;
+/// the root entry counts as a stream too, its contents being the mini stream
+pub trait StreamLocator<'a> {
+    fn locate(self, ole: &'a Ole) -> OleResult<&'a Entry>;
+}
+
+impl<'a> StreamLocator<'a> for &'a Entry {
+    fn locate(self, _ole: &'a Ole) -> OleResult<&'a Entry> {
+        Ok(self)
+    }
+}
+
+impl<'a> StreamLocator<'a> for usize {
+    fn locate(self, ole: &'a Ole) -> OleResult<&'a Entry> {
+        let entry = ole.entries_ref()?.get(self).ok_or(OleError::InvalidEntryIndex)?;
+        match entry.object_type {
+            ObjectType::Stream | ObjectType::RootStorage => Ok(entry),
+            _ => Err(OleError::NotAStream(self.to_string())),
+        }
+    }
+}
+
+impl<'a> StreamLocator<'a> for &str {
+    fn locate(self, ole: &'a Ole) -> OleResult<&'a Entry> {
+        let entry = &ole.entries_ref()?[ole.path_index(self)?];
+        match entry.object_type {
+            ObjectType::Stream | ObjectType::RootStorage => Ok(entry),
+            _ => Err(OleError::NotAStream(self.to_string())),
+        }
+    }
+}
+
+/// summarizes `body` instead of printing every sector, see `Ole::debug_dump`
+impl Debug for Ole {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Ole")
+            .field("header", &self.header)
+            .field("version", &self.version)
+            .field("entries", &self.entries_ref().ok().map(|v| v.len()))
+            .field("body", &format_args!("<{} sectors of {} bytes>", self.body.len(), self.header.sector_size()))
+            .finish_non_exhaustive()
+    }
+}
+
+struct Dump<'a>(&'a Ole);
+
+impl Debug for Dump<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let ole = self.0;
+        f.debug_struct("Ole")
+            .field("header", &ole.header)
+            .field("version", &ole.version)
+            .field("difat", &ole.difat)
+            .field("directory", &ole.directory)
+            .field("mini_fat", &ole.mini_fat)
+            .field("fat", &ole.fat)
+            .field("entries", &ole.entries)
+            .field("warnings", &ole.warnings)
+            .field("options", &ole.options)
+            .field("mini_fat_sectors", &ole.mini_fat_sectors)
+            .field("body", &ole.body)
+            .finish()
+    }
+}
+
 impl Ole {
+    /// everything, including the raw bytes of every sector
+    pub fn debug_dump(&self) -> String {
+        format!("{:#?}", Dump(self))
+    }
+
+    /// same as `open`
     pub fn from_path(path: &str) -> OleResult<Self> {
-        let buf = fs::read(path)?;
+        Self::open(path)
+    }
+
+    /// open a file with the default options, the usual way in; see `OleReader` to
+    /// configure the parse
+    ///
+    /// only the header and DIFAT are parsed here, the FAT, mini FAT and directory
+    /// follow on the first read or listing, and a file broken there fails that call
+    /// instead; `parse` finishes the job up front and adds its warnings to `warnings`
+    ///
+    /// ```
+    /// use rust_ole::ole::Ole;
+    ///
+    /// let ole = Ole::open("./abcd.doc").unwrap();
+    /// for (path, entry) in ole.iter_streams() {
+    ///     println!("{} {}", path, entry.stream_size);
+    /// }
+    /// assert_eq!(ole.read_stream("WordDocument").unwrap().len(), 6144);
+    /// ```
+    pub fn open<P: AsRef<Path>>(path: P) -> OleResult<Self> {
+        OleReader::new().lazy(true).open(path)
+    }
+
+    /// parse the DIFAT and leave the tables below it to the first read or listing
+    pub(crate) fn defer_tables(&mut self) -> OleResult<()> {
+        self.parse_difat()?;
+        self.deferred = Some(OnceLock::new());
+        Ok(())
+    }
+
+    /// a file already in memory, with nothing but the header parsed
+    pub fn from_bytes(buf: Vec<u8>) -> OleResult<Self> {
+        Self::from_slice(&buf)
+    }
 
+    /// same as `from_bytes`, the sectors are copied out of `buf`
+    pub fn from_slice(buf: &[u8]) -> OleResult<Self> {
+        Self::from_reader(Cursor::new(buf))
+    }
+
+    /// read a file starting at the reader's current position, which needn't be 0 when
+    /// the file is embedded in a larger container
+    pub fn from_reader<R: Read + Seek>(mut reader: R) -> OleResult<Self> {
+        let base = reader.stream_position()?;
+        let mut buf = [0u8; 512];
+        reader.read_exact(&mut buf).map_err(|_| OleError::InvalidFileFormat)?;
         let header = Header::read_le(&mut Cursor::new(&buf[..76]))?;
         let difat_entries = AllEntryDifat::read_le(&mut Cursor::new(&buf[76..512]))?;
-        let mut relative_pos = 512usize;
+        if header.sector_shift != 9 && header.sector_shift != 12 {
+            return Err(OleError::InvalidFileFormat);
+        }
+        // mini sectors are always 64 bytes, any other shift would size them past a
+        // sector or overflow
+        if header.mini_sector_shift != 6 {
+            return Err(OleError::InvalidFileFormat);
+        }
         let version = header.major_version.clone();
-        let sector_size = get_sector_size(&version);
-        //skip all bytes between header and difat
-        if version == MajorVersion::Version4 {
-            let len = sector_size - 512;
-            relative_pos = len * 8;
+        let sector_size = header.sector_size();
+        // the header takes up the whole first sector, zero padded past 512 bytes in
+        // version 4, and sector 0 starts right after it
+        let relative_pos = sector_size;
+
+        let mut warnings = vec![];
+        if !header.header_clsid_is_zero() {
+            warnings.push(ParseWarning::NonZeroHeaderClsid);
+        }
+        if !header.reserved_is_zero() {
+            warnings.push(ParseWarning::NonZeroReserved);
+        }
+        if version == MajorVersion::Version3 && header.number_of_directory_sectors != 0 {
+            warnings.push(ParseWarning::NonZeroDirectorySectorCount(header.number_of_directory_sectors));
         }
 
-        let body = buf[relative_pos..].chunks(sector_size).map(|v| v.to_vec()).collect::<Vec<Vec<u8>>>();
+        if reader.seek(SeekFrom::End(0))? < base + relative_pos as u64 {
+            return Err(OleError::InvalidFileFormat);
+        }
+        reader.seek(SeekFrom::Start(base + relative_pos as u64))?;
+        let mut rest = vec![];
+        reader.read_to_end(&mut rest)?;
+        let body = rest.chunks(sector_size).map(|v| v.to_vec()).collect::<Vec<Vec<u8>>>();
 
         Ok(Self {
             header,
@@ -63,38 +272,378 @@ impl Ole {
             directory: None,
             mini_fat: None,
             entries: None,
+            warnings,
+            mini_fat_sectors: vec![],
+            options: ParseOptions::default(),
+            directory_dirty: false,
+            deferred: None,
+            free_hint: 0,
         })
     }
 
     pub fn parse(&mut self) -> OleResult<()> {
+        self.parse_with_progress(|_| {})
+    }
+
+    /// `parse`, calling `progress` as each stage starts
+    pub fn parse_with_progress(&mut self, mut progress: impl FnMut(ParseStage)) -> OleResult<()> {
+        if self.deferred.is_some() {
+            return self.settle();
+        }
+        progress(ParseStage::Difat);
+        self.parse_difat()?;
+        let mut tables = Tables::default();
+        let parsed = self.parse_tables(&mut tables, progress);
+        self.install(tables);
+        parsed
+    }
+
+    pub fn parse_with_options(&mut self, options: ParseOptions) -> OleResult<()> {
+        self.options = options;
+        // tables a read already parsed went by the old options
+        self.deferred = self.deferred.take().map(|_| OnceLock::new());
+        self.parse()
+    }
+
+    /// take the file apart without cloning: the header, the directory entries, the
+    /// raw body sectors (everything after the header, in sector order) and the FAT;
+    /// entries and FAT are empty when the file hasn't been parsed
+    pub fn into_parts(mut self) -> (Header, Vec<Entry>, Vec<Vec<u8>>, Vec<SectorType>) {
+        let _ = self.settle();
+        (self.header, self.entries.unwrap_or_default(), self.body, self.fat.unwrap_or_default())
+    }
+
+    /// the cutoff actually used to tell mini streams from regular ones
+    pub fn mini_stream_cutoff(&self) -> u32 {
+        if self.options.honor_header_cutoff {
+            self.header.mini_stream_cutoff_size
+        } else {
+            SPEC_MINI_STREAM_CUTOFF
+        }
+    }
+
+    /// parse as much as possible for data recovery: a broken mini FAT is recorded in
+    /// `warnings` instead of failing, so only reads of mini streams fail afterwards
+    pub fn parse_partial(&mut self) -> OleResult<()> {
+        if self.deferred.is_some() {
+            return self.settle();
+        }
         self.parse_difat()?;
-        self.parse_fat()?;
-        self.parse_mini_fat()?;
-        self.parse_directory()
+        let mut tables = Tables::default();
+        let parsed = self.parse_fat(&mut tables).and_then(|_| {
+            if let Err(err) = self.parse_mini_fat(&mut tables) {
+                tables.mini_fat = None;
+                tables.warnings.push(ParseWarning::MiniFatUnreadable(err.to_string()));
+            }
+            self.parse_directory(&mut tables)
+        });
+        self.install(tables);
+        parsed
+    }
+
+    /// the FAT, mini FAT and directory stages of `parse`
+    fn parse_tables(&self, tables: &mut Tables, mut progress: impl FnMut(ParseStage)) -> OleResult<()> {
+        progress(ParseStage::Fat);
+        self.parse_fat(tables)?;
+        progress(ParseStage::MiniFat);
+        self.parse_mini_fat(tables)?;
+        progress(ParseStage::Directory);
+        self.parse_directory(tables)
+    }
+
+    /// move parsed tables into the fields, adding their warnings
+    fn install(&mut self, tables: Tables) {
+        let Tables { fat, mini_fat, mini_fat_sectors, directory, entries, warnings } = tables;
+        self.fat = fat;
+        self.mini_fat = mini_fat;
+        self.mini_fat_sectors = mini_fat_sectors;
+        self.directory = directory;
+        self.entries = entries;
+        self.warnings.extend(warnings);
+    }
+
+    /// the tables `open` deferred, parsed now unless a read already did
+    fn deferred(&self) -> OleResult<Option<&Tables>> {
+        let Some(cell) = &self.deferred else { return Ok(None) };
+        if let Some(tables) = cell.get() {
+            return Ok(Some(tables));
+        }
+        let mut tables = Tables::default();
+        self.parse_tables(&mut tables, |_| {})?;
+        Ok(Some(cell.get_or_init(|| tables)))
     }
 
+    /// finish what `open` deferred, before anything changes the file; a parse
+    /// failing here stays deferred, so reads keep reporting it
+    pub(crate) fn settle(&mut self) -> OleResult<()> {
+        let Some(cell) = self.deferred.take() else { return Ok(()) };
+        let tables = match cell.into_inner() {
+            Some(tables) => tables,
+            None => {
+                let mut tables = Tables::default();
+                if let Err(err) = self.parse_tables(&mut tables, |_| {}) {
+                    self.deferred = Some(OnceLock::new());
+                    return Err(err);
+                }
+                tables
+            }
+        };
+        self.install(tables);
+        Ok(())
+    }
+
+    /// read a whole stream through the chain its size implies; unless
+    /// `options.strict`, a read failing with `InvalidEntryChain`, a chain that loops
+    /// or ends before `stream_size` say, is retried through the other FAT
+    ///
+    /// reading the root entry yields the mini stream container, `stream_size` bytes
+    /// of its chain in the regular FAT
     pub fn read(&self, entry: &Entry) -> OleResult<Vec<u8>> {
-        let entry_size = entry.stream_size;
+        self.fat_ref()?;
+        if entry.chain.is_none() {
+            return Err(OleError::NoChain { path: self.path_of(entry) });
+        }
+        let entry_size = entry.effective_size(&self.version);
 
         if entry_size == 0 {
             return Err(OleError::InvalidEntrySize);
         }
 
-        if entry_size < self.header.mini_stream_cutoff_size as u64 {
-            self.get_mini_stream_data(&entry)
+        let mini = self.in_mini_stream(entry);
+        let data = if mini { self.get_mini_stream_data(entry) } else { self.get_stream_data(entry) };
+        if self.options.strict || !matches!(data, Err(OleError::InvalidEntryChain)) {
+            return data;
+        }
+
+        // recovery for files whose writer put a stream near the cutoff in the other
+        // FAT: follow the starting sector through the other table and keep that if
+        // it reads in full; only a chain the table ends with ENDOFCHAIN counts, so a
+        // start that lands on a FAT or free sector there isn't taken for data
+        let start = u32::from(entry.starting_sector_location.clone());
+        let table = if mini { self.fat_ref() } else { self.mini_fat_ref().map_err(|_| OleError::InvalidEntryChain) };
+        let Ok(table) = table else { return data };
+        let Ok(chain) = chain_iter(Ok(table), start, self.options.max_chain_len).collect::<OleResult<Vec<u32>>>() else {
+            return data;
+        };
+        if chain.last().and_then(|v| table.get(*v as usize)) != Some(&SectorType::EndOfChain) {
+            return data;
+        }
+        let other = Entry { chain: Some(chain.into_iter().map(SectorType::RegularSect).collect()), ..entry.clone() };
+        match if mini { self.get_stream_data(&other) } else { self.get_mini_stream_data(&other) } {
+            Ok(v) if v.len() as u64 == entry_size => Ok(v),
+            _ => data,
+        }
+    }
+
+    /// `read`, with an empty stream giving no bytes rather than `InvalidEntrySize`
+    pub(crate) fn read_contents(&self, entry: &Entry) -> OleResult<Vec<u8>> {
+        match entry.effective_size(&self.version) {
+            0 => Ok(vec![]),
+            _ => self.read(entry),
+        }
+    }
+
+    /// read a stream given by entry, index or path
+    pub fn read_stream<'a, L: StreamLocator<'a>>(&'a self, loc: L) -> OleResult<Vec<u8>> {
+        self.read(loc.locate(self)?)
+    }
+
+    /// read `len` bytes starting at `offset` of a stream, only touching the sectors
+    /// that overlap the range; returns fewer bytes when the range runs past the end
+    pub fn read_range(&self, entry: &Entry, offset: u64, len: usize) -> OleResult<Vec<u8>> {
+        self.read_span(entry, offset, len as u64)
+    }
+
+    /// `read` for untrusted input: a stream declaring more than `max_bytes` fails with
+    /// `StreamTooLarge` before anything is read; `read_range` from 0 gives the first
+    /// `max_bytes` instead
+    pub fn read_limited(&self, entry: &Entry, max_bytes: usize) -> OleResult<Vec<u8>> {
+        let size = entry.effective_size(&self.version);
+        if size > max_bytes as u64 {
+            return Err(OleError::StreamTooLarge { size, limit: max_bytes });
+        }
+        self.read(entry)
+    }
+
+    /// read `len` bytes starting at `offset` of the entry with the given directory id,
+    /// returning fewer bytes when the range runs past the end of the stream
+    pub fn read_range_by_id(&self, entry_id: u32, offset: u64, len: u64) -> OleResult<Vec<u8>> {
+        let entry = self.entries_ref()?.get(entry_id as usize).ok_or(OleError::InvalidEntryIndex)?;
+        self.read_span(entry, offset, len)
+    }
+
+    /// copy a byte range of a stream, skipping the sectors before `offset`
+    pub(crate) fn read_span(&self, entry: &Entry, offset: u64, len: u64) -> OleResult<Vec<u8>> {
+        let end = std::cmp::min(offset.saturating_add(len), entry.effective_size(&self.version));
+        let segments = self.stream_segments(entry)?;
+        // never reserve more than the chain actually holds, whatever the declared size
+        let available = segments.iter().map(|v| v.len as u64).sum::<u64>();
+        let mut data = Vec::with_capacity(std::cmp::min(end, available).saturating_sub(offset) as usize);
+        let mut pos = 0u64;
+        for segment in segments {
+            let next = pos + segment.len as u64;
+            if next > offset && pos < end {
+                let from = segment.start + offset.saturating_sub(pos) as usize;
+                let to = segment.start + (std::cmp::min(next, end) - pos) as usize;
+                let buf = self.body.get(segment.sector).ok_or(OleError::InvalidEntryIndex)?;
+                data.extend(buf.get(from..to).ok_or(OleError::InvalidEntrySize)?);
+            }
+            if next >= end {
+                break;
+            }
+            pos = next;
+        }
+        Ok(data)
+    }
+
+    /// locate every run of a stream's bytes in `body`, in order and trimmed to `stream_size`
+    pub(crate) fn stream_segments(&self, entry: &Entry) -> OleResult<Vec<Segment>> {
+        let chain = entry.chain.as_ref().ok_or(OleError::InvalidEntryChain)?;
+        let sector_size = self.header.sector_size();
+        let mut remaining = entry.effective_size(&self.version) as usize;
+        let mut segments = Vec::with_capacity(chain.len());
+        let mut visited = HashSet::new();
+
+        if self.in_mini_stream(entry) {
+            let mini_sector_size = self.mini_sector_size();
+            let container = self.entries_ref()?.first()
+                .and_then(|v| v.chain.as_ref())
+                .ok_or(OleError::InvalidEntryChain)?;
+            for item in chain {
+                if remaining == 0 {
+                    break;
+                }
+                let SectorType::RegularSect(idx) = item else { continue };
+                if !visited.insert(*idx) {
+                    return Err(OleError::InvalidEntryChain);
+                }
+                let offset = *idx as usize * mini_sector_size;
+                let Some(SectorType::RegularSect(sector)) = container.get(offset / sector_size) else {
+                    return Err(OleError::InvalidEntryChain);
+                };
+                let len = std::cmp::min(mini_sector_size, remaining);
+                segments.push(Segment { sector: *sector as usize, start: offset % sector_size, len });
+                remaining -= len;
+            }
+        } else {
+            for item in chain {
+                if remaining == 0 {
+                    break;
+                }
+                let SectorType::RegularSect(sector) = item else { continue };
+                if !visited.insert(*sector) {
+                    return Err(OleError::InvalidEntryChain);
+                }
+                let len = std::cmp::min(sector_size, remaining);
+                segments.push(Segment { sector: *sector as usize, start: 0, len });
+                remaining -= len;
+            }
+        }
+        Ok(segments)
+    }
+
+    /// read a stream without copying when its bytes are contiguous inside a single sector
+    ///
+    /// sectors are held separately in memory, so only streams living in one regular
+    /// sector or in consecutive mini sectors of one container sector can be borrowed,
+    /// everything else falls back to an owned `read`
+    pub fn read_cow(&self, entry: &Entry) -> OleResult<Cow<'_, [u8]>> {
+        if let Some(slice) = self.contiguous_slice(entry) {
+            return Ok(Cow::Borrowed(slice));
+        }
+        self.read(entry).map(Cow::Owned)
+    }
+
+    /// borrow a stream stored in the regular FAT that fits in a single sector,
+    /// `None` meaning it doesn't and `read` is needed
+    pub fn stream_single_sector(&self, entry: &Entry) -> OleResult<Option<&[u8]>> {
+        if entry.object_type != ObjectType::Stream || self.in_mini_stream(entry) {
+            return Ok(None);
+        }
+        let chain = entry.chain.as_ref().ok_or(OleError::InvalidEntryChain)?;
+        let [SectorType::RegularSect(sector)] = chain[..] else {
+            return Ok(None);
+        };
+        let buf = self.body.get(sector as usize).ok_or(OleError::InvalidEntryIndex)?;
+        buf.get(..entry.effective_size(&self.version) as usize).ok_or(OleError::InvalidEntrySize).map(Some)
+    }
+
+    fn contiguous_slice(&self, entry: &Entry) -> Option<&[u8]> {
+        let size = entry.effective_size(&self.version) as usize;
+        if size == 0 {
+            return None;
+        }
+        let chain = entry.chain.as_ref()?;
+        let indexes = chain.iter().map(|v| match v {
+            SectorType::RegularSect(v) => Some(*v as usize),
+            _ => None,
+        }).collect::<Option<Vec<usize>>>()?;
+        let first = *indexes.first()?;
+
+        let (sector, start) = if self.in_mini_stream(entry) {
+            if indexes.windows(2).any(|v| v[1] != v[0] + 1) {
+                return None;
+            }
+            let sector_size = self.header.sector_size();
+            let start = first * self.mini_sector_size();
+            let container = self.entries_ref().ok()?.first()?.chain.as_ref()?;
+            if (start + size - 1) / sector_size != start / sector_size {
+                return None;
+            }
+            match container.get(start / sector_size)? {
+                SectorType::RegularSect(v) => (*v as usize, start % sector_size),
+                _ => return None,
+            }
         } else {
-            self.get_stream_data(&entry)
+            if indexes.len() != 1 {
+                return None;
+            }
+            (first, 0)
+        };
+        self.body.get(sector)?.get(start..start + size)
+    }
+
+    /// whether a stream's bytes live in the mini stream; without a mini FAT in the
+    /// header, as some writers do, every stream is read through the regular FAT, and
+    /// an edit creating the mini FAT moves the small ones over first
+    pub(crate) fn in_mini_stream(&self, entry: &Entry) -> bool {
+        entry.object_type == ObjectType::Stream
+            && entry.effective_size(&self.version) < self.mini_stream_cutoff() as u64
+            && matches!(self.header.first_mini_fat_sector_location, SectorType::RegularSect(_))
+    }
+
+    pub(crate) fn mini_sector_size(&self) -> usize {
+        1 << self.header.mini_sector_shift
+    }
+
+    /// stream entries ordered by `stream_size`, largest first when `descending`
+    pub fn streams_by_size(&self, descending: bool) -> Vec<&Entry> {
+        let mut streams = self.entries_ref().into_iter()
+            .flatten()
+            .filter(|v| v.object_type == ObjectType::Stream)
+            .collect::<Vec<&Entry>>();
+        streams.sort_by_key(|v| v.effective_size(&self.version));
+        if descending {
+            streams.reverse();
         }
+        streams
     }
 
     fn parse_difat(&mut self) -> OleResult<()> {
-        let count = get_sector_size(&self.version) / 4;
+        // the last slot of a difat sector holds the next pointer
+        let count = self.header.sector_size() / 4 - 1;
         let Header { first_difat_sector_location, .. } = &self.header;
 
+        let mut walked = 0;
         // if there are more difat sectors
         if let SectorType::RegularSect(idx) = first_difat_sector_location {
             let mut current_idx = *idx as usize;
             loop {
+                // more DIFAT sectors than the file has sectors, the chain loops
+                if walked >= self.body.len() {
+                    return Err(OleError::InvalidDifat);
+                }
+                walked += 1;
                 let buf: &Vec<u8> = self.body.get(current_idx).ok_or(OleError::InvalidDifat)?;
 
                 let Difat { entries, next } = Difat::read_le_args(&mut Cursor::new(&buf), (count as u16,))?;
@@ -107,45 +656,52 @@ impl Ole {
                 }
             }
         }
+        if walked != self.header.number_of_difat_sectors as usize {
+            return Err(OleError::DifatCountMismatch { expected: self.header.number_of_difat_sectors as usize, found: walked });
+        }
         Ok(())
     }
 
-    fn parse_fat(&mut self) -> OleResult<()> {
-        let count = get_sector_size(&self.version) / 4;
+    /// the FAT is the concatenation of the sectors listed by the DIFAT, in DIFAT order:
+    /// the N-th one holds the entries for sectors N * count onwards, wherever it sits
+    /// in the file itself
+    fn parse_fat(&self, tables: &mut Tables) -> OleResult<()> {
+        let count = self.header.sector_size() / 4;
         let Header { number_of_fat_sectors, .. } = &self.header;
 
         if *number_of_fat_sectors as usize != self.difat.len() {
-            return Err(OleError::InvalidDifat);
+            return Err(OleError::FatCountMismatch { expected: *number_of_fat_sectors as usize, found: self.difat.len() });
         }
 
         for sector in &self.difat {
             if let SectorType::RegularSect(idx) = sector {
                 let buf: &Vec<u8> = self.body.get(*idx as usize).ok_or(OleError::InvalidEntryIndex)?;
                 let fat = Fat::read_le_args(&mut Cursor::new(&buf), (count as u16,))?;
-                if self.fat.is_some() {
-                    self.fat.as_mut().unwrap().extend(fat.entries);
-                } else {
-                    self.fat = Some(fat.entries);
+                match tables.fat.as_mut() {
+                    Some(entries) => entries.extend(fat.entries),
+                    None => tables.fat = Some(fat.entries),
                 }
             }
         }
+        debug_assert_eq!(tables.fat.as_ref().map_or(0, |v| v.len()), self.difat.len() * count);
 
         Ok(())
     }
 
-    fn parse_mini_fat(&mut self) -> OleResult<()> {
-        let count = get_sector_size(&self.version) / 4;
+    fn parse_mini_fat(&self, tables: &mut Tables) -> OleResult<()> {
+        let count = self.header.sector_size() / 4;
         let Header { first_mini_fat_sector_location, .. } = &self.header;
 
         if let SectorType::RegularSect(_) = first_mini_fat_sector_location {
-            for sector in self.get_fat_chain(first_mini_fat_sector_location) {
+            let chain = self.chain_through(tables.fat.as_ref().ok_or(OleError::NotParsed), first_mini_fat_sector_location)?;
+            tables.mini_fat_sectors = chain.clone();
+            for sector in chain {
                 if let SectorType::RegularSect(v) = sector {
                     let buf: &Vec<u8> = self.body.get(v as usize).ok_or(OleError::InvalidEntryIndex)?;
                     let mini_fat = MiniFat::read_le_args(&mut Cursor::new(&buf), (count as u16,))?;
-                    if self.mini_fat.is_some() {
-                        self.mini_fat.as_mut().unwrap().extend(mini_fat.entries);
-                    } else {
-                        self.mini_fat = Some(mini_fat.entries);
+                    match tables.mini_fat.as_mut() {
+                        Some(entries) => entries.extend(mini_fat.entries),
+                        None => tables.mini_fat = Some(mini_fat.entries),
                     }
                 }
             }
@@ -153,134 +709,1216 @@ impl Ole {
         Ok(())
     }
 
-    fn parse_directory(&mut self) -> OleResult<()> {
-        let count = if self.version == MajorVersion::Version3 { 4 } else { 32 };
+    fn parse_directory(&self, tables: &mut Tables) -> OleResult<()> {
+        let count = self.header.sector_size() / 128;
 
-        let Header { first_directory_sector_location, mini_stream_cutoff_size, .. } = &self.header;
+        let Header { first_directory_sector_location, .. } = &self.header;
+        let has_mini_fat = matches!(self.header.first_mini_fat_sector_location, SectorType::RegularSect(_));
+        let mini_stream_cutoff_size = self.mini_stream_cutoff();
+        let fat = tables.fat.as_ref();
 
         if let SectorType::RegularSect(_) = first_directory_sector_location {
-            let directories = self.get_fat_chain(first_directory_sector_location);
-            let entries = directories.iter().flat_map(|directory| {
-                if let SectorType::RegularSect(v) = directory {
-                    let buf = self.body.get(*v as usize);
-                    if buf.is_none() {
-                        return vec![];
+            let directories = self.chain_through(fat.ok_or(OleError::NotParsed), first_directory_sector_location)?;
+            tables.directory = Some(directories.clone());
+            let mut entries = vec![];
+            let mut broken = vec![];
+            for directory in &directories {
+                let SectorType::RegularSect(v) = directory else { continue };
+                let Some(buf) = self.body.get(*v as usize) else { continue };
+                // unless `options.strict` a bad sector only costs its own entries, its
+                // slots stay empty so the entries after it keep their stream IDs
+                let directory = match Directory::read_le_args(&mut Cursor::new(&buf), (count as u16,)) {
+                    Ok(directory) => directory,
+                    Err(err) if self.options.strict => return Err(err.into()),
+                    Err(err) => {
+                        broken.push(ParseWarning::DirectorySectorUnreadable(*v, err.to_string()));
+                        entries.extend((0..count).map(|_| Entry::unused()));
+                        continue;
                     }
-                    let buf = buf.unwrap();
-                    let directory = match Directory::read_le_args(&mut Cursor::new(&buf), (count as u16,)) {
-                        Ok(directory) => directory,
-                        Err(err) => {
-                            println!("Error: {}", err);
-                            return vec![];
+                };
+
+                for mut entry in directory.entries {
+                    let Entry { starting_sector_location, object_type, .. } = &entry;
+                    let size = entry.effective_size(&self.version);
+                    let chain = match object_type {
+                        ObjectType::Stream if has_mini_fat && size < mini_stream_cutoff_size as u64 => {
+                            tables.mini_fat.as_ref().map(|v| self.chain_through(Ok(v), starting_sector_location))
                         }
+                        ObjectType::Stream | ObjectType::RootStorage => Some(self.chain_through(fat.ok_or(OleError::NotParsed), starting_sector_location)),
+                        _ => None,
                     };
+                    // a looping or dangling chain only costs that stream, it's left
+                    // without a chain and reported in `warnings`
+                    match chain {
+                        Some(Ok(chain)) => entry.append_chain(chain),
+                        Some(Err(err)) => broken.push(ParseWarning::BrokenChain(entry.name().into_owned(), err.to_string())),
+                        None => {}
+                    }
+                    entries.push(entry);
+                }
+            }
 
-                    directory.entries.into_iter().map(|mut entry| {
-                        let Entry { starting_sector_location, object_type, stream_size, .. } = &entry;
-                        match object_type {
-                            ObjectType::Stream => {
-                                if *stream_size < *mini_stream_cutoff_size as u64 {
-                                    let chain = self.get_mini_fat_chain(starting_sector_location);
-                                    entry.append_chain(chain);
-                                } else {
-                                    let chain = self.get_fat_chain(starting_sector_location);
-                                    entry.append_chain(chain);
-                                }
-                            }
-                            ObjectType::RootStorage => {
-                                let chain = self.get_fat_chain(starting_sector_location);
-                                entry.append_chain(chain);
-                            }
-                            _ => {}
-                        }
-                        entry
-                    }).collect::<Vec<Entry>>()
-                } else {
-                    vec![]
+            tables.entries = Some(entries);
+            tables.warnings.extend(broken);
+        }
+
+        // everything else hangs off entry 0, it has to be the root storage
+        match tables.entries.as_ref().and_then(|v| v.first()) {
+            Some(root) if root.object_type == ObjectType::RootStorage => Ok(()),
+            _ => Err(OleError::InvalidRootEntry),
+        }
+    }
+
+    /// a table out of the fields, or out of what `open` deferred
+    fn table<'a, T>(&'a self, own: &'a Option<T>, deferred: fn(&Tables) -> &Option<T>) -> OleResult<&'a T> {
+        match self.deferred()? {
+            Some(tables) => deferred(tables),
+            None => own,
+        }.as_ref().ok_or(OleError::NotParsed)
+    }
+
+    pub(crate) fn entries_ref(&self) -> OleResult<&Vec<Entry>> {
+        self.table(&self.entries, |v| &v.entries)
+    }
+
+    pub(crate) fn fat_ref(&self) -> OleResult<&Vec<SectorType>> {
+        self.table(&self.fat, |v| &v.fat)
+    }
+
+    pub(crate) fn mini_fat_ref(&self) -> OleResult<&Vec<SectorType>> {
+        self.table(&self.mini_fat, |v| &v.mini_fat)
+    }
+
+    pub(crate) fn directory_ref(&self) -> OleResult<&Vec<SectorType>> {
+        self.table(&self.directory, |v| &v.directory)
+    }
+
+    /// sectors holding the mini FAT, empty until parsed
+    pub(crate) fn mini_fat_sectors_ref(&self) -> &[SectorType] {
+        match self.deferred() {
+            Ok(Some(tables)) => &tables.mini_fat_sectors,
+            _ => &self.mini_fat_sectors,
+        }
+    }
+
+    /// the FAT value recorded for a regular sector
+    pub fn fat_entry(&self, sector: usize) -> OleResult<SectorType> {
+        self.fat_ref()?.get(sector).cloned().ok_or(OleError::InvalidEntryIndex)
+    }
+
+    /// the mini FAT value recorded for a mini sector
+    pub fn mini_fat_entry(&self, sector: usize) -> OleResult<SectorType> {
+        self.mini_fat_ref()?.get(sector).cloned().ok_or(OleError::InvalidEntryIndex)
+    }
+
+    /// how many leading bytes of the file hold everything needed to list its
+    /// contents: the header plus every DIFAT, FAT, mini FAT and directory sector
+    ///
+    /// stream data may lie further out, a downloader can parse as soon as this
+    /// much has arrived
+    pub fn metadata_byte_requirement(&self) -> OleResult<usize> {
+        let directory = self.directory_ref()?;
+        let highest = self.difat.iter()
+            .chain(directory)
+            .chain(self.mini_fat_sectors_ref())
+            .filter_map(|v| match v {
+                SectorType::RegularSect(v) => Some(*v as usize),
+                _ => None,
+            })
+            .chain(self.difat_chain()?)
+            .max();
+        let sector_size = self.header.sector_size();
+        Ok(sector_size + highest.map_or(0, |v| (v + 1) * sector_size))
+    }
+
+    /// sectors holding the FAT, in order, as listed by the DIFAT
+    pub fn fat_sector_indices(&self) -> Vec<u32> {
+        self.difat.iter().map(|v| u32::from(v.clone())).collect()
+    }
+
+    /// sectors of the DIFAT chain beyond the 109 entries kept in the header, empty
+    /// when the chain can't be followed
+    pub fn difat_sector_indices(&self) -> Vec<u32> {
+        self.difat_chain().unwrap_or_default().into_iter().map(|v| v as u32).collect()
+    }
+
+    /// rough number of heap bytes held, dominated by the sectors of `body`; meant for
+    /// bounding how many files are kept in memory at once
+    pub fn estimated_heap_bytes(&self) -> usize {
+        let sector = std::mem::size_of::<SectorType>();
+        let tables = [
+            Some(&self.difat[..]),
+            self.directory_ref().ok().map(Vec::as_slice),
+            self.fat_ref().ok().map(Vec::as_slice),
+            self.mini_fat_ref().ok().map(Vec::as_slice),
+            Some(self.mini_fat_sectors_ref()),
+        ];
+        let entries = self.entries_ref().into_iter().flatten()
+            .map(|v| std::mem::size_of::<Entry>() + v.chain.as_ref().map_or(0, |c| c.len() * sector))
+            .sum::<usize>();
+        self.body.iter().map(|v| v.len()).sum::<usize>()
+            + self.body.len() * std::mem::size_of::<Vec<u8>>()
+            + tables.iter().flatten().map(|v| std::mem::size_of_val(*v)).sum::<usize>()
+            + entries
+    }
+
+    /// whether the entry at `index` carries exactly this CLSID, in on-disk byte order;
+    /// use `clsid_from_str` to get that from a GUID string
+    pub fn entry_has_clsid(&self, index: usize, clsid: &[u8; 16]) -> bool {
+        self.entries_ref().ok().and_then(|v| v.get(index)).is_some_and(|v| v.clsid == *clsid)
+    }
+
+    /// 64-bit FNV-1a hash of every sector in `body`, in sector order, for spotting
+    /// identical sectors within a file or across files
+    pub fn sector_hashes(&self) -> Vec<u64> {
+        self.body.iter().map(|sector| {
+            sector.iter().fold(0xCBF29CE484222325u64, |hash, byte| (hash ^ *byte as u64).wrapping_mul(0x100000001B3))
+        }).collect()
+    }
+
+    /// sectors allocated in the FAT that no structure or directory entry reaches
+    ///
+    /// reachable sectors are the FAT and DIFAT sectors, the directory, mini FAT and
+    /// mini stream container chains, and the chains of every regular stream
+    pub fn orphaned_sectors(&self) -> OleResult<Vec<usize>> {
+        let fat = self.fat_ref()?;
+        let entries = self.entries_ref()?;
+        let mut reachable = vec![false; fat.len()];
+        let mut mark = |sectors: &[SectorType]| {
+            for sector in sectors {
+                if let Some(v) = reachable.get_mut(u32::from(sector.clone()) as usize) {
+                    *v = true;
                 }
-            }).collect::<Vec<Entry>>();
+            }
+        };
 
-            self.entries = Some(entries);
+        mark(&self.difat);
+        mark(&self.difat_chain()?.into_iter().map(|v| SectorType::RegularSect(v as u32)).collect::<Vec<SectorType>>());
+        mark(&self.get_fat_chain(&self.header.first_directory_sector_location)?);
+        mark(&self.get_fat_chain(&self.header.first_mini_fat_sector_location)?);
+        for entry in entries {
+            let regular = match entry.object_type {
+                ObjectType::RootStorage => true,
+                ObjectType::Stream => !self.in_mini_stream(entry),
+                _ => false,
+            };
+            if let (true, Some(chain)) = (regular, &entry.chain) {
+                mark(chain);
+            }
         }
 
-        Ok(())
+        Ok(fat.iter().enumerate()
+            .filter(|(i, v)| **v != SectorType::FreeSect && !reachable[*i])
+            .map(|(i, _)| i)
+            .collect())
     }
 
-    fn get_fat_chain(&self, index: &SectorType) -> Vec<SectorType> {
-        let mut cur = index;
+    /// sector indices of the DIFAT chain, following the next pointer at the end of each sector
+    pub(crate) fn difat_chain(&self) -> OleResult<Vec<usize>> {
+        let sector_size = self.header.sector_size();
         let mut result = vec![];
+        let mut cur = self.header.first_difat_sector_location.clone();
         while let SectorType::RegularSect(v) = cur {
-            result.push(SectorType::RegularSect(*v));
-            cur = &self.fat.as_ref().unwrap()[*v as usize];
+            if result.len() >= self.body.len() {
+                return Err(OleError::InvalidDifat);
+            }
+            let buf = self.body.get(v as usize).ok_or(OleError::InvalidDifat)?;
+            let next = buf.get(sector_size - 4..sector_size).ok_or(OleError::InvalidDifat)?;
+            result.push(v as usize);
+            cur = SectorType::from(u32::from_le_bytes(next.try_into().unwrap()));
         }
-        result
+        Ok(result)
     }
 
-    fn get_mini_fat_chain(&self, index: &SectorType) -> Vec<SectorType> {
-        let mut cur = index;
-        let mut result = vec![];
-        while let SectorType::RegularSect(v) = cur {
-            result.push(SectorType::RegularSect(*v));
-            cur = &self.mini_fat.as_ref().unwrap()[*v as usize];
+    /// path of an entry of this file, found by address; just its name for an entry
+    /// that isn't in the tree
+    fn path_of(&self, entry: &Entry) -> String {
+        let entries = self.entries_ref().map(Vec::as_slice).unwrap_or_default();
+        self.entry_paths().unwrap_or_default().into_iter()
+            .find(|(_, idx)| std::ptr::eq(&entries[*idx], entry))
+            .map_or_else(|| entry.name().into_owned(), |(path, _)| path)
+    }
+
+    /// search the red-black tree under a storage for a direct child with the given name
+    pub(crate) fn find_child(&self, storage: usize, name: &str) -> OleResult<Option<usize>> {
+        let entries = self.entries_ref()?;
+        let mut cur = &entries.get(storage).ok_or(OleError::InvalidEntryIndex)?.child_id;
+        // a well-formed tree can't be deeper than the number of entries
+        for _ in 0..entries.len() {
+            let SectorType::RegularSect(v) = cur else {
+                return Ok(None);
+            };
+            let entry = entries.get(*v as usize).ok_or(OleError::InvalidEntryIndex)?;
+            cur = match compare_names(name, &entry.name()) {
+                Ordering::Less => &entry.left_sibling_id,
+                Ordering::Greater => &entry.right_sibling_id,
+                Ordering::Equal => return Ok(Some(*v as usize)),
+            };
+        }
+        Err(OleError::InvalidEntryChain)
+    }
+
+    /// first entry anywhere in the directory with this name, compared case-insensitively
+    /// the way sibling trees order names
+    pub fn find_entry(&self, name: &str) -> Option<&Entry> {
+        self.entries_ref().into_iter().flatten()
+            .find(|v| v.object_type != ObjectType::Unknown && compare_names(&v.name(), name) == Ordering::Equal)
+    }
+
+    /// `find_entry`, mutably
+    pub fn find_entry_mut(&mut self, name: &str) -> Option<&mut Entry> {
+        self.entries_mut().into_iter().flatten()
+            .find(|v| v.object_type != ObjectType::Unknown && compare_names(&v.name(), name) == Ordering::Equal)
+    }
+
+    /// a stream directly under the root, looked up without any path handling
+    pub fn root_stream(&self, name: &str) -> OleResult<&Entry> {
+        let idx = self.find_child(0, name)?.ok_or_else(|| OleError::PathNotFound(name.to_string()))?;
+        let entry = &self.entries_ref()?[idx];
+        match entry.object_type {
+            ObjectType::Stream => Ok(entry),
+            _ => Err(OleError::NotAStream(name.to_string())),
+        }
+    }
+
+    /// the entry at a `/`-separated path, following each storage's sibling tree down
+    /// from the root; the leading `/` is optional and so is the root's own name, as in
+    /// `/Root Entry/\u{1}Ole10Native`, unless the root has a child by that name
+    ///
+    /// fails with `PathNotFound` for a missing component and `NotAStorage` when a
+    /// component other than the last is a stream
+    pub fn resolve_path(&self, path: &str) -> OleResult<&Entry> {
+        let entries = self.entries_ref()?;
+        let root = entries.first().ok_or(OleError::InvalidRootEntry)?;
+        let path = path.trim_start_matches('/');
+        let (first, tail) = path.split_once('/').unwrap_or((path, ""));
+        let rest = if compare_names(first, &root.name()) == Ordering::Equal && self.find_child(0, first)?.is_none() {
+            tail
+        } else {
+            path
+        };
+        Ok(&entries[self.path_index(rest)?])
+    }
+
+    /// resolve a `/`-separated path below the root storage to an entry index
+    pub(crate) fn path_index(&self, path: &str) -> OleResult<usize> {
+        let entries = self.entries_ref()?;
+        let mut cur = 0;
+        let mut walked = String::new();
+        for name in path.split('/').filter(|v| !v.is_empty()) {
+            if entries[cur].object_type == ObjectType::Stream {
+                return Err(OleError::NotAStorage(walked));
+            }
+            cur = self.find_child(cur, name)?
+                .ok_or_else(|| OleError::PathNotFound(path.to_string()))?;
+            walked = format!("{}/{}", walked, name);
         }
-        result
+        Ok(cur)
+    }
+
+    /// the FAT chain starting at `index`, `InvalidEntryChain` when it loops
+    pub(crate) fn get_fat_chain(&self, index: &SectorType) -> OleResult<Vec<SectorType>> {
+        self.chain_through(self.fat_ref(), index)
+    }
+
+    /// the chain starting at `index` in a FAT or mini FAT
+    fn chain_through(&self, table: OleResult<&Vec<SectorType>>, index: &SectorType) -> OleResult<Vec<SectorType>> {
+        chain_iter(table, u32::from(index.clone()), self.options.max_chain_len).map(|v| v.map(SectorType::RegularSect)).collect()
+    }
+
+    /// walk a FAT chain lazily from `start`, one sector index at a time
+    ///
+    /// a chain longer than the FAT must loop back on itself, it ends with
+    /// `InvalidEntryChain` instead of going round forever; a link outside the FAT
+    /// ends it with `InvalidEntryIndex`
+    pub fn fat_chain_iter(&self, start: u32) -> impl Iterator<Item = OleResult<u32>> + '_ {
+        chain_iter(self.fat_ref(), start, self.options.max_chain_len)
     }
 
     /// mini stream data sector chain is stored in root entry
     /// and because it's size is 64 bytes, so we should map the index in chain to a real sector index
+    /// and the offset of the mini sector within it
     fn get_mini_stream_data(&self, entry: &Entry) -> OleResult<Vec<u8>> {
-        let mini_sector_size = self.header.mini_sector_shift as usize;
-        let sector_size = get_sector_size(&self.version);
+        let mini_sector_size = self.mini_sector_size();
+        let sector_size = self.header.sector_size();
 
         let count = sector_size / mini_sector_size;
 
-        let size = entry.stream_size as usize;
+        let size = entry.effective_size(&self.version) as usize;
         let chain = entry.chain.as_ref().ok_or(OleError::InvalidEntryChain)?;
 
-        let mini_stream_chain = self.entries.as_ref().ok_or(OleError::InvalidEntryChain)?[0]
-            .chain.as_ref().ok_or(OleError::InvalidEntryChain)?;
+        let mini_stream_chain = self.entries_ref()?.first()
+            .and_then(|v| v.chain.as_ref()).ok_or(OleError::InvalidEntryChain)?;
 
         let mut total_read: usize = 0;
-        let mut data = vec![];
+        let mut data = Vec::with_capacity(std::cmp::min(size, chain.len() * mini_sector_size));
+        let mut visited = HashSet::new();
         for item in chain {
-            match item {
-                SectorType::RegularSect(idx) => {
-                    let sector_cur = &mini_stream_chain[*idx as usize / count];
-                    if let SectorType::RegularSect(v) = sector_cur {
-                        let cur = *v as usize;
-                        let buf: &Vec<u8> = self.body.get(cur).ok_or(OleError::InvalidEntryIndex)?;
-                        let start = cur * mini_sector_size;
-                        let end = start + std::cmp::min(mini_sector_size, size - total_read);
-                        data.extend(&buf[start..end]);
-                        total_read += end - start;
-                    }
+            // the last mini sector only holds what's left of `stream_size`
+            if total_read == size {
+                break;
+            }
+            if let SectorType::RegularSect(idx) = item {
+                if !visited.insert(*idx) {
+                    return Err(OleError::InvalidEntryChain);
+                }
+                let sector_cur = mini_stream_chain.get(*idx as usize / count).ok_or(OleError::InvalidEntryChain)?;
+                if let SectorType::RegularSect(v) = sector_cur {
+                    let cur = *v as usize;
+                    let buf: &Vec<u8> = self.body.get(cur).ok_or(OleError::InvalidEntryIndex)?;
+                    let start = (*idx as usize % count) * mini_sector_size;
+                    let end = start + std::cmp::min(mini_sector_size, size - total_read);
+                    data.extend(buf.get(start..end).ok_or(OleError::InvalidEntrySize)?);
+                    total_read += end - start;
                 }
-                _ => {}
             }
         }
+        // the chain ended before `stream_size` bytes
+        if total_read < size {
+            return Err(OleError::InvalidEntryChain);
+        }
 
         Ok(data)
     }
 
     fn get_stream_data(&self, entry: &Entry) -> OleResult<Vec<u8>> {
-        let size = entry.stream_size as usize;
-        let sector_size = get_sector_size(&self.version);
+        let size = entry.effective_size(&self.version) as usize;
+        let sector_size = self.header.sector_size();
         let chain = entry.chain.as_ref().ok_or(OleError::InvalidEntryChain)?;
         let mut total_read: usize = 0;
-        let mut data = vec![];
+        // the declared size can't be trusted, the chain bounds what can be read
+        let mut data = Vec::with_capacity(std::cmp::min(size, chain.len() * sector_size));
+        // a chain revisiting a sector is a cycle, fail before its bytes go in twice
+        let mut visited = HashSet::new();
         for item in chain {
-            match item {
-                SectorType::RegularSect(idx) => {
-                    let cur = *idx as usize;
-                    let buf: &Vec<u8> = self.body.get(cur).ok_or(OleError::InvalidEntryIndex)?;
-                    let end = std::cmp::min(sector_size, size - total_read);
-                    data.extend(&buf[0..end]);
-                    total_read += end;
+            if let SectorType::RegularSect(idx) = item {
+                if !visited.insert(*idx) {
+                    return Err(OleError::InvalidEntryChain);
+                }
+                let cur = *idx as usize;
+                let buf: &Vec<u8> = self.body.get(cur).ok_or(OleError::InvalidEntryIndex)?;
+                let wanted = std::cmp::min(sector_size, size - total_read);
+                // a file cut off mid-sector leaves a short last sector in `body`
+                let end = std::cmp::min(wanted, buf.len());
+                data.extend(&buf[0..end]);
+                total_read += end;
+                if end < wanted {
+                    return Err(OleError::TruncatedStream { expected: size as u64, found: total_read as u64 });
                 }
-                _ => {}
             }
         }
+        if total_read < size {
+            return Err(OleError::InvalidEntryChain);
+        }
 
         Ok(data)
     }
-}
\ No newline at end of file
+}
+
+/// follow a chain through a FAT or mini FAT for at most `limit` links, failing the
+/// table's error up front
+fn chain_iter(table: OleResult<&Vec<SectorType>>, start: u32, limit: usize) -> impl Iterator<Item = OleResult<u32>> + '_ {
+    let (table, mut error) = match table {
+        Ok(v) => (&v[..], None),
+        Err(err) => (&[][..], Some(err)),
+    };
+    let mut cur = SectorType::from(start);
+    let mut steps = 0;
+    std::iter::from_fn(move || {
+        let SectorType::RegularSect(v) = cur else { return None };
+        cur = SectorType::EndOfChain;
+        if let Some(err) = error.take() {
+            return Some(Err(err));
+        }
+        if steps >= std::cmp::min(table.len(), limit) {
+            return Some(Err(OleError::InvalidEntryChain));
+        }
+        steps += 1;
+        match table.get(v as usize) {
+            Some(next) => {
+                cur = next.clone();
+                Some(Ok(v))
+            }
+            None => Some(Err(OleError::InvalidEntryIndex)),
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::common::{MajorVersion, OleError, OleResult, ParseWarning, SectorType};
+    use crate::directory::{clsid_from_str, Entry, ObjectType};
+    use crate::ole::{Ole, ParseOptions, ParseStage};
+    use crate::test_util::*;
+    use std::borrow::Cow;
+
+    #[test]
+    fn from_bytes() {
+        let bytes = build(vec![stream("WordDocument", pattern(6144, 7))]);
+        let mut ole = Ole::from_bytes(bytes.clone()).unwrap();
+        ole.parse().unwrap();
+        assert_eq!(ole.read_stream("WordDocument").unwrap(), pattern(6144, 7));
+
+        for len in [0, 75, 100, 511] {
+            assert!(matches!(Ole::from_slice(&bytes[..len]), Err(OleError::InvalidFileFormat)));
+        }
+    }
+
+    #[test]
+    fn from_reader() {
+        let mut container = b"PK not really a zip".to_vec();
+        let start = container.len() as u64;
+        container.extend(build(vec![stream("WordDocument", pattern(6144, 7))]));
+
+        let mut reader = std::io::Cursor::new(container);
+        reader.set_position(start);
+        let mut ole = Ole::from_reader(reader).unwrap();
+        ole.parse().unwrap();
+        assert_eq!(ole.read_stream("WordDocument").unwrap(), pattern(6144, 7));
+    }
+
+    #[test]
+    fn from_reader_rejects_mini_sector_shift() {
+        let bytes = build(vec![stream("Small", pattern(100, 1))]);
+        for shift in [0u16, 5, 10, 63] {
+            let mut bytes = bytes.clone();
+            bytes[0x20..0x22].copy_from_slice(&shift.to_le_bytes());
+            assert!(matches!(Ole::from_slice(&bytes), Err(OleError::InvalidFileFormat)));
+        }
+        assert_eq!(open(&bytes).read_stream("Small").unwrap(), pattern(100, 1));
+    }
+
+    #[test]
+    fn unparsed_reads_fail_with_not_parsed() {
+        let ole = open_unparsed(&build(vec![stream("WordDocument", pattern(6144, 7))]));
+        let mut entry = Entry::new("WordDocument", ObjectType::Stream);
+        entry.stream_size = 6144;
+        assert!(matches!(ole.read(&entry), Err(OleError::NotParsed)));
+        entry.stream_size = 100;
+        assert!(matches!(ole.read(&entry), Err(OleError::NotParsed)));
+        assert!(matches!(ole.read_stream("WordDocument"), Err(OleError::NotParsed)));
+        assert!(matches!(ole.verify(), Err(OleError::NotParsed)));
+        assert!(!ole.to_string().is_empty());
+    }
+
+    #[test]
+    fn parse_rejects_non_root_first_entry() {
+        let mut bytes = build(vec![stream("WordDocument", pattern(6144, 7))]);
+        // entry 0 of the directory in sector 1, object type at 0x42: make it a stream
+        bytes[sector_offset(1) + 0x42] = 0x02;
+        let mut ole = open_unparsed(&bytes);
+        assert!(matches!(ole.parse(), Err(OleError::InvalidRootEntry)));
+    }
+
+    #[test]
+    fn read_caps_allocation_at_chain_length() {
+        let mut bytes = build(vec![stream("WordDocument", pattern(6144, 7))]);
+        // WordDocument is entry 1 of the directory in sector 1, its size is at 0x78
+        let offset = sector_offset(1) + 128 + 0x78;
+        bytes[offset..offset + 8].copy_from_slice(&u64::MAX.to_le_bytes());
+        let ole = open(&bytes);
+        // the chain ends long before the declared size, which fails before allocating
+        assert!(matches!(ole.read_stream("WordDocument"), Err(OleError::InvalidEntryChain)));
+        assert_eq!(ole.read_range_by_id(1, 0, u64::MAX).unwrap(), pattern(6144, 7));
+        assert_eq!(ole.read_range_by_id(1, 6000, u64::MAX).unwrap(), pattern(6144, 7)[6000..]);
+    }
+
+    #[test]
+    fn resolve_path() {
+        let ole = open(&build(vec![
+            stream("WordDocument", pattern(100, 7)),
+            storage("ObjectPool", vec![storage("_1234", vec![stream("\u{1}Ole10Native", pattern(20, 1))])]),
+        ]));
+        let native = ole.resolve_path("/ObjectPool/_1234/\u{1}Ole10Native").unwrap();
+        assert_eq!(native.stream_size, 20);
+        assert!(std::ptr::eq(ole.resolve_path("/Root Entry/ObjectPool/_1234/\u{1}Ole10Native").unwrap(), native));
+        assert!(std::ptr::eq(ole.resolve_path("objectpool/_1234/\u{1}OLE10NATIVE").unwrap(), native));
+        assert_eq!(ole.resolve_path("/").unwrap().object_type, ObjectType::RootStorage);
+        assert_eq!(ole.resolve_path("/Root Entry").unwrap().object_type, ObjectType::RootStorage);
+
+        assert!(matches!(ole.resolve_path("/ObjectPool/_9999"), Err(OleError::PathNotFound(_))));
+        match ole.resolve_path("/WordDocument/Inner") {
+            Err(OleError::NotAStorage(path)) => assert_eq!(path, "/WordDocument"),
+            other => panic!("unexpected {:?}", other.map(|v| v.name().into_owned())),
+        }
+    }
+
+    #[test]
+    fn find_entry() {
+        let mut ole = open(&build(vec![
+            stream("WordDocument", pattern(100, 7)),
+            storage("ObjectPool", vec![stream("\u{1}CompObj", pattern(20, 1))]),
+            stream("Документ", pattern(10, 2)),
+        ]));
+        assert_eq!(ole.find_entry("worddocument").unwrap().stream_size, 100);
+        assert_eq!(ole.find_entry("\u{1}COMPOBJ").unwrap().stream_size, 20);
+        assert_eq!(ole.find_entry("документ").unwrap().stream_size, 10);
+        assert!(ole.find_entry("WordDoc").is_none());
+        assert!(ole.find_entry("").is_none());
+
+        ole.find_entry_mut("OBJECTPOOL").unwrap().state_bits = 7;
+        assert_eq!(ole.find_entry("ObjectPool").unwrap().state_bits, 7);
+    }
+
+    #[test]
+    fn root_stream() {
+        let mut ole = Ole::from_path("./abcd.doc").unwrap();
+        ole.parse().unwrap();
+        let entry = ole.root_stream("WordDocument").unwrap();
+        assert_eq!(entry.stream_size, 6144);
+        assert_eq!(ole.read(entry).unwrap(), pattern(6144, 7));
+        assert!(ole.root_stream("worddocument").is_ok());
+        assert!(matches!(ole.root_stream("Data"), Err(OleError::PathNotFound(_))));
+
+        let ole = open(&build(vec![storage("ObjectPool", vec![])]));
+        assert!(matches!(ole.root_stream("ObjectPool"), Err(OleError::NotAStream(_))));
+    }
+
+    #[test]
+    fn into_parts() {
+        let bytes = build(vec![stream("WordDocument", pattern(6144, 7))]);
+        let ole = open(&bytes);
+        let expected = ole.clone();
+        let (header, entries, body, fat) = ole.into_parts();
+        assert_eq!(header.number_of_fat_sectors, expected.header.number_of_fat_sectors);
+        assert_eq!(entries.iter().map(|v| v.name().into_owned()).collect::<Vec<String>>(), vec!["Root Entry", "WordDocument", "", ""]);
+        assert_eq!(body, expected.body);
+        assert_eq!(body.concat(), bytes[512..]);
+        assert_eq!(fat, expected.fat.unwrap());
+
+        let (_, entries, body, fat) = open_unparsed(&bytes).into_parts();
+        assert!(entries.is_empty() && fat.is_empty());
+        assert_eq!(body.len(), 14);
+    }
+
+    #[test]
+    fn parse_with_progress() {
+        let mut ole = open_unparsed(&build(vec![stream("WordDocument", pattern(6144, 7))]));
+        let mut stages = vec![];
+        ole.parse_with_progress(|stage| stages.push(stage)).unwrap();
+        assert_eq!(stages, vec![ParseStage::Difat, ParseStage::Fat, ParseStage::MiniFat, ParseStage::Directory]);
+
+        let mut bytes = build(vec![stream("WordDocument", pattern(6144, 7))]);
+        // claim a second FAT sector the DIFAT doesn't list
+        bytes[0x2C..0x30].copy_from_slice(&2u32.to_le_bytes());
+        let mut stages = vec![];
+        assert!(open_unparsed(&bytes).parse_with_progress(|stage| stages.push(stage)).is_err());
+        assert_eq!(stages, vec![ParseStage::Difat, ParseStage::Fat]);
+    }
+
+    #[test]
+    fn open_defers_tables_to_first_read() {
+        let eager = crate::reader::OleReader::new().open("./abcd.doc").unwrap();
+        let ole = Ole::open("./abcd.doc").unwrap();
+        assert_eq!(ole.difat, eager.difat);
+        assert!(ole.fat.is_none() && ole.mini_fat.is_none() && ole.entries.is_none());
+        assert!(ole.deferred.as_ref().unwrap().get().is_none());
+
+        // the first read parses the rest, once
+        assert_eq!(ole.read_stream("WordDocument").unwrap(), eager.read_stream("WordDocument").unwrap());
+        let tables = ole.deferred.as_ref().unwrap().get().unwrap();
+        assert_eq!(tables.fat, eager.fat);
+        assert_eq!(tables.mini_fat, eager.mini_fat);
+        assert_eq!(ole.read_all_streams().unwrap(), eager.read_all_streams().unwrap());
+        assert!(std::ptr::eq(ole.deferred.as_ref().unwrap().get().unwrap(), tables));
+
+        // so does a listing
+        let ole = Ole::open("./abcd.doc").unwrap();
+        assert_eq!(ole.iter_streams().count(), eager.iter_streams().count());
+        assert!(ole.deferred.as_ref().unwrap().get().is_some());
+
+        // `parse` moves the tables into the fields
+        let mut ole = Ole::open("./abcd.doc").unwrap();
+        ole.parse().unwrap();
+        assert!(ole.deferred.is_none());
+        assert_eq!((&ole.fat, &ole.mini_fat, &ole.warnings), (&eager.fat, &eager.mini_fat, &eager.warnings));
+        assert_eq!(ole.to_string(), eager.to_string());
+    }
+
+    #[test]
+    fn parse_partial_tolerates_corrupt_mini_fat() {
+        let mut bytes = build(vec![
+            stream("WordDocument", pattern(6144, 7)),
+            stream("Small", pattern(100, 1)),
+        ]);
+        // point the mini FAT at a sector past the end of the file
+        bytes[0x3C..0x40].copy_from_slice(&100u32.to_le_bytes());
+
+        assert!(open_unparsed(&bytes).parse().is_err());
+
+        let mut ole = open_unparsed(&bytes);
+        ole.parse_partial().unwrap();
+        assert!(matches!(ole.warnings[..], [ParseWarning::MiniFatUnreadable(_)]));
+        let entries = ole.entries.as_ref().unwrap();
+        assert_eq!(ole.read(&entries[1]).unwrap(), pattern(6144, 7));
+        assert!(ole.read(&entries[2]).is_err());
+    }
+
+    #[test]
+    fn small_streams_without_mini_fat() {
+        // lay the small stream out in the regular FAT with no mini FAT, then claim
+        // the usual cutoff
+        let mut bytes = build_with_cutoff(64, vec![
+            stream("WordDocument", pattern(6144, 7)),
+            stream("Small", pattern(300, 1)),
+        ]);
+        bytes[0x38..0x3C].copy_from_slice(&4096u32.to_le_bytes());
+        let ole = open(&bytes);
+        assert!(ole.mini_fat.is_none());
+        let entry = &ole.entries.as_ref().unwrap()[2];
+        assert_eq!(ole.read(entry).unwrap(), pattern(300, 1));
+        assert_eq!(ole.read_cow(entry).unwrap(), pattern(300, 1));
+        assert_eq!(ole.read_span(entry, 100, 50).unwrap(), pattern(300, 1)[100..150]);
+        assert!(ole.orphaned_sectors().unwrap().is_empty());
+    }
+
+    #[test]
+    fn fat_chain_iter() {
+        let mut ole = open(&build(vec![stream("WordDocument", pattern(6144, 7))]));
+        let start = u32::from(ole.entries.as_ref().unwrap()[1].starting_sector_location.clone());
+        let chain = ole.fat_chain_iter(start).collect::<OleResult<Vec<u32>>>().unwrap();
+        let expected = ole.get_fat_chain(&SectorType::RegularSect(start)).unwrap();
+        assert_eq!(chain.len(), 12);
+        assert_eq!(chain.into_iter().map(SectorType::RegularSect).collect::<Vec<SectorType>>(), expected);
+        assert_eq!(ole.fat_chain_iter(0xFFFFFFFE).count(), 0);
+
+        // loop the last sector back to the first
+        let last = u32::from(expected[11].clone()) as usize;
+        ole.fat.as_mut().unwrap()[last] = SectorType::RegularSect(start);
+        let chain = ole.fat_chain_iter(start).collect::<Vec<OleResult<u32>>>();
+        assert!(matches!(chain.last(), Some(Err(OleError::InvalidEntryChain))));
+        assert!(ole.fat_chain_iter(start).collect::<OleResult<Vec<u32>>>().is_err());
+
+        ole.fat.as_mut().unwrap()[last] = SectorType::RegularSect(100_000);
+        assert!(matches!(ole.fat_chain_iter(start).last(), Some(Err(OleError::InvalidEntryIndex))));
+
+        let unparsed = open_unparsed(&build(vec![]));
+        assert!(matches!(unparsed.fat_chain_iter(0).collect::<Vec<OleResult<u32>>>()[..], [Err(OleError::NotParsed)]));
+    }
+
+    #[test]
+    fn unreadable_directory_sector() {
+        let names = ["A", "B", "C", "D", "E", "F"];
+        let mut bytes = build(names.iter().map(|v| stream(v, pattern(100, 1))).collect());
+        let second = u32::from(open(&bytes).directory.unwrap()[1].clone());
+        // an invalid colour flag in the first entry of the second directory sector
+        bytes[sector_offset(second as usize) + 67] = 7;
+
+        let ole = open(&bytes);
+        assert!(matches!(&ole.warnings[..], [ParseWarning::DirectorySectorUnreadable(v, _)] if *v == second));
+        let entries = ole.entries.as_ref().unwrap();
+        assert_eq!(entries.len(), 8);
+        assert!(entries[4..].iter().all(|v| v.object_type == ObjectType::Unknown));
+        assert_eq!(ole.read(&entries[1]).unwrap(), pattern(100, 1));
+
+        let mut ole = open_unparsed(&bytes);
+        ole.options.strict = true;
+        assert!(matches!(ole.parse(), Err(OleError::ParseError(_))));
+    }
+
+    #[test]
+    fn looping_chains_are_detected() {
+        let mut bytes = build(vec![
+            stream("WordDocument", pattern(6144, 7)),
+            stream("Small", pattern(300, 1)),
+        ]);
+        let ole = open(&bytes);
+        let chain = ole.entries.as_ref().unwrap()[1].chain.clone().unwrap();
+        let fat = ole.fat.as_ref().unwrap();
+        assert_eq!(ole.get_fat_chain(&chain[0]).unwrap(), chain);
+        assert!(ole.get_fat_chain(&SectorType::EndOfChain).unwrap().is_empty());
+
+        // point the 6th sector of the stream back at its 4th
+        let (fifth, third) = (u32::from(chain[5].clone()), u32::from(chain[3].clone()));
+        assert_eq!(fat[fifth as usize], chain[6]);
+        let at = sector_offset(0) + fifth as usize * 4;
+        bytes[at..at + 4].copy_from_slice(&third.to_le_bytes());
+        let ole = open(&bytes);
+        assert!(matches!(ole.get_fat_chain(&chain[0]), Err(OleError::InvalidEntryChain)));
+        assert!(ole.entries.as_ref().unwrap()[1].chain.is_none());
+        assert!(matches!(&ole.warnings[..], [ParseWarning::BrokenChain(name, _)] if name == "WordDocument"));
+        assert!(ole.read_stream("WordDocument").is_err());
+        assert_eq!(ole.read_stream("Small").unwrap(), pattern(300, 1));
+
+        let mut ole = open(&build(vec![stream("Small", pattern(300, 1))]));
+        let start = ole.entries.as_ref().unwrap()[1].starting_sector_location.clone();
+        ole.mini_fat.as_mut().unwrap()[2] = SectorType::RegularSect(0);
+        assert!(matches!(ole.chain_through(ole.mini_fat_ref(), &start), Err(OleError::InvalidEntryChain)));
+        ole.mini_fat = None;
+        assert!(matches!(ole.chain_through(ole.mini_fat_ref(), &start), Err(OleError::NotParsed)));
+    }
+
+    #[test]
+    fn fat_and_difat_count_mismatch() {
+        let bytes = build(vec![stream("WordDocument", pattern(6144, 7))]);
+        let parse = |bytes: &[u8]| {
+            let mut ole = open_unparsed(bytes);
+            ole.parse()
+        };
+
+        let mut patched = bytes.clone();
+        patched[0x2C..0x30].copy_from_slice(&3u32.to_le_bytes());
+        let err = parse(&patched).unwrap_err();
+        assert!(matches!(err, OleError::FatCountMismatch { expected: 3, found: 1 }));
+        assert_eq!(err.to_string(), "Fat Count Mismatch: header says 3, DIFAT lists 1");
+
+        let mut patched = bytes.clone();
+        patched[0x48..0x4C].copy_from_slice(&1u32.to_le_bytes());
+        assert!(matches!(parse(&patched), Err(OleError::DifatCountMismatch { expected: 1, found: 0 })));
+
+        assert!(parse(&bytes).is_ok());
+    }
+
+    #[test]
+    fn cyclic_chain_read_fails_without_data() {
+        let mut ole = open(&build(vec![
+            stream("WordDocument", pattern(6144, 7)),
+            stream("Small", pattern(300, 1)),
+        ]));
+        // chains revisiting their 4th sector, as a chain walk that missed the cycle
+        // would have produced them
+        for idx in [1, 2] {
+            let entry = &mut ole.entries.as_mut().unwrap()[idx];
+            let chain = entry.chain.clone().unwrap();
+            entry.chain = Some([&chain[..4], &chain[3..]].concat());
+        }
+        let entries = ole.entries.clone().unwrap();
+        for strict in [false, true] {
+            ole.options.strict = strict;
+            for entry in &entries[1..3] {
+                assert!(matches!(ole.read(entry), Err(OleError::InvalidEntryChain)));
+                assert!(matches!(ole.read_range(entry, 0, 64), Err(OleError::InvalidEntryChain)));
+                assert!(ole.open_stream(entry).is_err());
+            }
+        }
+    }
+
+    #[test]
+    fn chains_fail_without_panicking() {
+        let bytes = build(vec![
+            stream("WordDocument", pattern(6144, 7)),
+            stream("Small", pattern(300, 1)),
+        ]);
+        let unparsed = open_unparsed(&bytes);
+        assert!(matches!(unparsed.get_fat_chain(&SectorType::RegularSect(0)), Err(OleError::NotParsed)));
+        assert!(matches!(unparsed.chain_through(unparsed.mini_fat_ref(), &SectorType::RegularSect(0)), Err(OleError::NotParsed)));
+        let entry = Entry::new("WordDocument", ObjectType::Stream);
+        assert!(matches!(unparsed.read(&entry), Err(OleError::NotParsed)));
+
+        let mut ole = open(&bytes);
+        let len = ole.fat.as_ref().unwrap().len() as u32;
+        let last = u32::from(ole.entries.as_ref().unwrap()[1].chain.as_ref().unwrap()[11].clone());
+        ole.fat.as_mut().unwrap()[last as usize] = SectorType::RegularSect(len + 5);
+        assert!(matches!(ole.get_fat_chain(&SectorType::RegularSect(last)), Err(OleError::InvalidEntryIndex)));
+        assert!(matches!(ole.get_fat_chain(&SectorType::RegularSect(len)), Err(OleError::InvalidEntryIndex)));
+
+        ole.entries.as_mut().unwrap().clear();
+        let small = Entry { stream_size: 300, chain: Some(vec![SectorType::RegularSect(0)]), ..entry };
+        assert!(ole.read(&small).is_err());
+    }
+
+    #[test]
+    fn read_mini_stream() {
+        let ole = open(&build(vec![
+            stream("A", pattern(150, 1)),
+            stream("B", pattern(50, 2)),
+        ]));
+        assert_eq!(ole.mini_sector_size(), 64);
+        assert_eq!(ole.read_stream("B").unwrap(), pattern(50, 2));
+
+        // B takes mini sectors 6 to 9, crossing into the second container sector
+        let ole = open(&build(vec![
+            stream("A", pattern(384, 1)),
+            stream("B", pattern(200, 2)),
+        ]));
+        assert_eq!(ole.read_stream("A").unwrap(), pattern(384, 1));
+        assert_eq!(ole.read_stream("B").unwrap(), pattern(200, 2));
+
+        let ole = Ole::open("./abcd.doc").unwrap();
+        let entry = ole.root_stream("\u{1}CompObj").unwrap();
+        assert_eq!(ole.read(entry).unwrap(), ole.read_span(entry, 0, entry.stream_size).unwrap());
+    }
+
+    #[test]
+    fn read_mini_stream_trims_last_mini_sector() {
+        let mut ole = open(&build(vec![
+            stream("A", pattern(70, 1)),
+            stream("B", pattern(100, 2)),
+        ]));
+        let idx = ole.path_index("A").unwrap();
+        assert_eq!(ole.entries.as_ref().unwrap()[idx].chain.as_ref().unwrap().len(), 2);
+        assert_eq!(ole.read_stream("A").unwrap(), pattern(70, 1));
+
+        // a chain running past the stream's size still yields exactly 70 bytes
+        ole.entries.as_mut().unwrap()[idx].chain.as_mut().unwrap().push(SectorType::RegularSect(2));
+        let data = ole.read_stream("A").unwrap();
+        assert_eq!(data.len(), 70);
+        assert_eq!(data[64..], pattern(70, 1)[64..]);
+    }
+
+    #[test]
+    fn read_root_entry() {
+        let ole = open(&build(vec![
+            stream("WordDocument", pattern(6144, 7)),
+            stream("A", pattern(150, 1)),
+            stream("B", pattern(200, 2)),
+        ]));
+        let root = &ole.entries.as_ref().unwrap()[0];
+        let container = ole.read(root).unwrap();
+        assert_eq!(container.len() as u64, root.stream_size);
+        assert_eq!(container.len(), 192 + 256);
+        assert_eq!(container[..150], pattern(150, 1));
+        assert_eq!(container[192..392], pattern(200, 2));
+        assert_eq!(ole.read_stream("/").unwrap(), container);
+        assert_eq!(ole.read_stream(0).unwrap(), container);
+    }
+
+    #[test]
+    fn read_falls_back_to_the_other_fat() {
+        // lay out a stream one byte under the cutoff in the regular FAT, then claim
+        // the usual cutoff so its size says mini stream
+        let mut bytes = build_with_cutoff(4095, vec![
+            stream("Edge", pattern(4095, 7)),
+            stream("Small", pattern(100, 1)),
+        ]);
+        bytes[0x38..0x3C].copy_from_slice(&4096u32.to_le_bytes());
+        let mut ole = open(&bytes);
+        let entry = ole.entries.as_ref().unwrap()[1].clone();
+        assert_eq!(entry.stream_size, 4095);
+        assert_eq!(ole.read(&entry).unwrap(), pattern(4095, 7));
+
+        ole.options.strict = true;
+        assert_ne!(ole.read(&entry).ok(), Some(pattern(4095, 7)));
+    }
+
+    #[test]
+    fn read_without_chain_names_the_entry() {
+        let mut bytes = build(vec![
+            stream("WordDocument", pattern(6144, 7)),
+            storage("Nested", vec![stream("Small", pattern(100, 1))]),
+        ]);
+        bytes[0x3C..0x40].copy_from_slice(&100u32.to_le_bytes());
+        let mut ole = open_unparsed(&bytes);
+        ole.parse_partial().unwrap();
+
+        let entry = ole.path_index("/Nested/Small").map(|v| &ole.entries.as_ref().unwrap()[v]).unwrap();
+        match ole.read(entry) {
+            Err(OleError::NoChain { path }) => assert_eq!(path, "/Nested/Small"),
+            other => panic!("unexpected {:?}", other.map(|v| v.len())),
+        }
+    }
+
+    #[test]
+    fn streams_by_size() {
+        let ole = open(&build(vec![
+            stream("Small", pattern(100, 1)),
+            stream("WordDocument", pattern(6144, 7)),
+            storage("Storage", vec![stream("Medium", pattern(4608, 2))]),
+        ]));
+        let sizes = ole.streams_by_size(true).iter().map(|v| v.stream_size).collect::<Vec<u64>>();
+        assert_eq!(sizes, vec![6144, 4608, 100]);
+        assert_eq!(ole.streams_by_size(true)[0].name(), "WordDocument");
+        assert_eq!(ole.streams_by_size(false)[0].name(), "Small");
+    }
+
+    #[test]
+    fn fat_entry() {
+        let ole = open(&build(vec![
+            stream("WordDocument", pattern(1024 * 5, 7)),
+            stream("Small", pattern(100, 1)),
+        ]));
+        // FAT, directory, mini FAT, mini stream container, then WordDocument at 4..14
+        assert_eq!(ole.fat_entry(0).unwrap(), SectorType::FatSect);
+        assert_eq!(ole.fat_entry(4).unwrap(), SectorType::RegularSect(5));
+        assert_eq!(ole.fat_entry(13).unwrap(), SectorType::EndOfChain);
+        assert_eq!(ole.fat_entry(14).unwrap(), SectorType::FreeSect);
+        assert!(ole.fat_entry(128).is_err());
+
+        assert_eq!(ole.mini_fat_entry(0).unwrap(), SectorType::RegularSect(1));
+        assert_eq!(ole.mini_fat_entry(1).unwrap(), SectorType::EndOfChain);
+
+        assert!(matches!(ole.mini_fat_entry(128), Err(OleError::InvalidEntryIndex)));
+
+        assert!(open_unparsed(&build(vec![])).fat_entry(0).is_err());
+        assert!(matches!(open_unparsed(&build(vec![])).mini_fat_entry(0), Err(OleError::NotParsed)));
+    }
+
+    #[test]
+    fn orphaned_sectors() {
+        let mut ole = open(&build(vec![
+            stream("WordDocument", pattern(4608, 7)),
+            stream("Gone", pattern(4608, 1)),
+        ]));
+        assert!(ole.orphaned_sectors().unwrap().is_empty());
+
+        // dropping the entry leaves its chain allocated in the FAT
+        ole.entries.as_mut().unwrap()[2].object_type = ObjectType::Unknown;
+        assert_eq!(ole.orphaned_sectors().unwrap(), (11..20).collect::<Vec<usize>>());
+    }
+
+    #[test]
+    fn fat_and_difat_sector_indices() {
+        let mut bytes = build(vec![stream("WordDocument", pattern(6144, 7))]);
+        let ole = open(&bytes);
+        assert_eq!(ole.fat_sector_indices(), vec![0]);
+        assert!(ole.difat_sector_indices().is_empty());
+
+        // move the FAT sector's location out of the header into an appended DIFAT sector
+        let difat = ((bytes.len() - 512) / 512) as u32;
+        bytes.extend(0u32.to_le_bytes());
+        bytes.extend(std::iter::repeat_n(0xFFu8, 126 * 4));
+        bytes.extend(0xFFFFFFFEu32.to_le_bytes());
+        bytes[76..80].copy_from_slice(&0xFFFFFFFFu32.to_le_bytes());
+        bytes[0x44..0x48].copy_from_slice(&difat.to_le_bytes());
+        bytes[0x48..0x4C].copy_from_slice(&1u32.to_le_bytes());
+        let at = sector_offset(0) + difat as usize * 4;
+        bytes[at..at + 4].copy_from_slice(&0xFFFFFFFCu32.to_le_bytes());
+
+        let ole = open(&bytes);
+        assert_eq!(ole.fat_sector_indices(), vec![0]);
+        assert_eq!(ole.difat_sector_indices(), vec![difat]);
+        assert_eq!(ole.read_stream("WordDocument").unwrap(), pattern(6144, 7));
+    }
+
+    #[test]
+    fn fat_sectors_out_of_physical_order() {
+        let mut bytes = build(vec![stream("WordDocument", pattern(70_000, 7))]);
+        let ole = open(&bytes);
+        assert_eq!(ole.fat_sector_indices(), vec![0, 1]);
+
+        // move the second FAT sector behind everything else, freeing its old place
+        let moved = (bytes.len() - 512) / 512;
+        let mut second = bytes[sector_offset(1)..sector_offset(2)].to_vec();
+        let at = (moved - 128) * 4;
+        second[at..at + 4].copy_from_slice(&0xFFFFFFFDu32.to_le_bytes());
+        bytes.extend(second);
+        bytes[sector_offset(0) + 4..sector_offset(0) + 8].copy_from_slice(&0xFFFFFFFFu32.to_le_bytes());
+        bytes[80..84].copy_from_slice(&(moved as u32).to_le_bytes());
+
+        let ole = open(&bytes);
+        assert_eq!(ole.fat_sector_indices(), vec![0, moved as u32]);
+        assert_eq!(ole.fat_entry(1).unwrap(), SectorType::FreeSect);
+        assert_eq!(ole.fat_entry(moved).unwrap(), SectorType::FatSect);
+        assert_eq!(ole.read_stream("WordDocument").unwrap(), pattern(70_000, 7));
+        assert!(ole.orphaned_sectors().unwrap().is_empty());
+    }
+
+    #[test]
+    fn estimated_heap_bytes() {
+        let bytes = build(vec![
+            stream("WordDocument", pattern(60_000, 7)),
+            stream("Small", pattern(300, 1)),
+        ]);
+        let ole = open(&bytes);
+        let estimate = ole.estimated_heap_bytes();
+        assert!(estimate > bytes.len() - 512, "{} for {} bytes", estimate, bytes.len());
+        assert!(estimate < bytes.len() * 5 / 4, "{} for {} bytes", estimate, bytes.len());
+        assert!(open_unparsed(&bytes).estimated_heap_bytes() < estimate);
+    }
+
+    #[test]
+    fn metadata_byte_requirement() {
+        let bytes = build(vec![
+            stream("WordDocument", pattern(6144, 7)),
+            stream("Small", pattern(100, 1)),
+        ]);
+        // FAT, directory, mini FAT, then the container and WordDocument
+        let ole = open(&bytes);
+        assert_eq!(ole.metadata_byte_requirement().unwrap(), 512 + 3 * 512);
+        assert!(matches!(open_unparsed(&bytes).metadata_byte_requirement(), Err(OleError::NotParsed)));
+
+        let partial = open(&bytes[..2048]);
+        assert_eq!(partial.iter_streams().count(), 2);
+    }
+
+    #[test]
+    fn entry_has_clsid() {
+        let mut bytes = build(vec![storage("Equation", vec![]), stream("Data", pattern(10, 1))]);
+        // the storage is entry 1 of the directory in sector 1, its CLSID is at 0x50
+        let offset = sector_offset(1) + 128 + 0x50;
+        bytes[offset..offset + 16].copy_from_slice(&[0x02, 0xCE, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0xC0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x46]);
+        let ole = open(&bytes);
+
+        let equation = clsid_from_str("{0002CE02-0000-0000-C000-000000000046}").unwrap();
+        assert!(ole.entry_has_clsid(1, &equation));
+        assert!(!ole.entry_has_clsid(0, &equation));
+        assert!(!ole.entry_has_clsid(2, &equation));
+        assert!(!ole.entry_has_clsid(99, &equation));
+        assert!(ole.entry_has_clsid(2, &[0; 16]));
+        assert_eq!(clsid_from_str("0002CE02-0000-0000-C000-000000000046"), Some(equation));
+        assert_eq!(clsid_from_str("{0002CE02-0000-0000-C000}"), None);
+    }
+
+    #[test]
+    fn sector_hashes() {
+        let ole = open(&build(vec![
+            stream("First", pattern(5120, 1)),
+            stream("Second", pattern(5120, 1)),
+            stream("Third", pattern(5120, 2)),
+        ]));
+        let hashes = ole.sector_hashes();
+        assert_eq!(hashes.len(), ole.body.len());
+        // FAT, directory, then ten sectors per stream
+        assert_eq!(hashes[2..12], hashes[12..22]);
+        assert_ne!(hashes[2..12], hashes[22..32]);
+        assert_ne!(hashes[0], hashes[1]);
+    }
+
+    #[test]
+    fn parse_options_cutoff() {
+        let bytes = build_with_cutoff(8192, vec![stream("Between", pattern(6000, 3))]);
+
+        let ole = open(&bytes);
+        assert_eq!(ole.mini_stream_cutoff(), 8192);
+        assert_eq!(ole.entries.as_ref().unwrap()[1].chain.as_ref().unwrap().len(), 94);
+
+        let mut ole = open_unparsed(&bytes);
+        ole.parse_with_options(ParseOptions { honor_header_cutoff: false, ..Default::default() }).unwrap();
+        assert_eq!(ole.mini_stream_cutoff(), 4096);
+        assert_ne!(ole.entries.as_ref().unwrap()[1].chain.as_ref().unwrap().len(), 94);
+    }
+
+    #[test]
+    fn read_range_by_id() {
+        let ole = open(&build(vec![
+            stream("WordDocument", pattern(6144, 7)),
+            stream("Small", pattern(100, 1)),
+        ]));
+        assert_eq!(ole.read_range_by_id(1, 1000, 600).unwrap(), pattern(6144, 7)[1000..1600]);
+        assert_eq!(ole.read_range_by_id(2, 50, 30).unwrap(), pattern(100, 1)[50..80]);
+        // ranges past the end are clamped
+        assert_eq!(ole.read_range_by_id(2, 90, 30).unwrap(), pattern(100, 1)[90..]);
+        assert!(ole.read_range_by_id(2, 200, 30).unwrap().is_empty());
+        assert!(ole.read_range_by_id(9, 0, 1).is_err());
+    }
+
+    #[test]
+    fn read_limited() {
+        let ole = open(&build(vec![
+            stream("WordDocument", pattern(6144, 7)),
+            stream("Small", pattern(100, 1)),
+        ]));
+        let entries = ole.entries.as_ref().unwrap();
+        let err = ole.read_limited(&entries[1], 4096).unwrap_err();
+        assert!(matches!(err, OleError::StreamTooLarge { size: 6144, limit: 4096 }));
+        assert_eq!(err.to_string(), "Stream Too Large: 6144 bytes, limit 4096");
+        assert_eq!(ole.read_limited(&entries[1], 6144).unwrap(), pattern(6144, 7));
+        assert_eq!(ole.read_limited(&entries[2], 4096).unwrap(), pattern(100, 1));
+    }
+
+    #[test]
+    fn version3_ignores_stream_size_high_word() {
+        let mut ole = open(&build(vec![
+            stream("WordDocument", pattern(6144, 7)),
+            stream("Small", pattern(100, 1)),
+        ]));
+        for entry in &mut ole.entries.as_mut().unwrap()[1..3] {
+            entry.stream_size |= 0xDEAD_0000_0000;
+        }
+        let entries = ole.entries.as_ref().unwrap().clone();
+        assert_eq!(entries[1].effective_size(&ole.version), 6144);
+        assert!(ole.in_mini_stream(&entries[2]));
+        assert_eq!(ole.read(&entries[1]).unwrap(), pattern(6144, 7));
+        assert_eq!(ole.read(&entries[2]).unwrap(), pattern(100, 1));
+        assert_eq!(ole.read_limited(&entries[1], 6144).unwrap(), pattern(6144, 7));
+        assert_eq!(ole.stream_descriptors()[1].size, 6144);
+
+        ole.version = MajorVersion::Version4;
+        let err = ole.read_limited(&entries[1], 6144).unwrap_err();
+        assert!(matches!(err, OleError::StreamTooLarge { size: 0xDEAD_0000_1800, .. }));
+    }
+
+    #[test]
+    fn read_truncated_stream() {
+        let mut bytes = build(vec![
+            stream("WordDocument", pattern(6000, 7)),
+            stream("Small", pattern(100, 1)),
+        ]);
+        // WordDocument is the last thing in the file, its last sector holds 368 bytes
+        bytes.truncate(bytes.len() - 512 + 100);
+        let ole = open(&bytes);
+        let err = ole.read_stream("WordDocument").unwrap_err();
+        assert!(matches!(err, OleError::TruncatedStream { expected: 6000, found: 5732 }));
+        assert_eq!(err.to_string(), "Truncated Stream: expected 6000 bytes, file holds 5732");
+        assert_eq!(ole.read_stream("Small").unwrap(), pattern(100, 1));
+    }
+
+    #[test]
+    fn read_range() {
+        let ole = open(&build(vec![
+            stream("WordDocument", pattern(6144, 7)),
+            stream("Small", pattern(100, 1)),
+        ]));
+        let entries = ole.entries.as_ref().unwrap();
+        let (large, small) = (&entries[1], &entries[2]);
+        assert_eq!(ole.read_range(large, 0x400 + 100, 1000).unwrap(), pattern(6144, 7)[0x464..0x464 + 1000]);
+        assert_eq!(ole.read_range(large, 6100, 256).unwrap(), pattern(6144, 7)[6100..]);
+        assert_eq!(ole.read_range(small, 60, 10).unwrap(), pattern(100, 1)[60..70]);
+        assert!(ole.read_range(small, 100, 10).unwrap().is_empty());
+        assert!(ole.read_range(large, u64::MAX, usize::MAX).unwrap().is_empty());
+    }
+
+    #[test]
+    fn debug_is_bounded() {
+        let small = open(&build(vec![stream("Small", pattern(100, 1))]));
+        let large = open(&build(vec![stream("Large", pattern(512 * 200, 1))]));
+        let small_len = format!("{:?}", small).len();
+        let large_len = format!("{:?}", large).len();
+        assert!(large_len < 2048);
+        assert!(large_len.abs_diff(small_len) < 16);
+        assert!(format!("{:?}", large).contains("<203 sectors of 512 bytes>"));
+        assert!(large.debug_dump().len() > 512 * 200);
+
+        // a lazily opened file counts the entries it parses on demand
+        let lazy = Ole::open("./abcd.doc").unwrap();
+        let count = lazy.entries_ref().unwrap().len();
+        assert!(format!("{:?}", lazy).contains(&format!("entries: Some({})", count)));
+    }
+
+    #[test]
+    fn read_stream() {
+        let ole = open(&build(vec![
+            storage("Storage", vec![stream("WordDocument", pattern(6144, 7))]),
+        ]));
+        let entry = &ole.entries.as_ref().unwrap()[2];
+        let expected = pattern(6144, 7);
+        assert_eq!(ole.read_stream(entry).unwrap(), expected);
+        assert_eq!(ole.read_stream(2).unwrap(), expected);
+        assert_eq!(ole.read_stream("Storage/WordDocument").unwrap(), expected);
+        assert!(ole.read_stream(1).is_err());
+        assert!(ole.read_stream("Storage").is_err());
+        assert!(ole.read_stream("Missing").is_err());
+    }
+
+    #[test]
+    fn read_cow() {
+        let ole = open(&build(vec![
+            stream("WordDocument", pattern(6144, 7)),
+            stream("Small", pattern(100, 1)),
+        ]));
+        let entries = ole.entries.as_ref().unwrap();
+
+        let data = ole.read_cow(&entries[2]).unwrap();
+        assert!(matches!(data, Cow::Borrowed(_)));
+        assert_eq!(&data[..], &pattern(100, 1)[..]);
+
+        let data = ole.read_cow(&entries[1]).unwrap();
+        assert!(matches!(data, Cow::Owned(_)));
+        assert_eq!(&data[..], &pattern(6144, 7)[..]);
+    }
+
+    #[test]
+    fn stream_single_sector() {
+        let ole = open(&build_with_cutoff(64, vec![
+            stream("One", pattern(300, 1)),
+            stream("Two", pattern(700, 2)),
+            stream("Mini", pattern(40, 3)),
+        ]));
+        let entries = ole.entries.as_ref().unwrap();
+        let one = &entries[ole.path_index("One").unwrap()];
+        assert_eq!(ole.stream_single_sector(one).unwrap().unwrap(), &ole.read(one).unwrap()[..]);
+        assert_eq!(ole.stream_single_sector(one).unwrap().unwrap(), &pattern(300, 1)[..]);
+        assert_eq!(ole.stream_single_sector(&entries[ole.path_index("Two").unwrap()]).unwrap(), None);
+        assert_eq!(ole.stream_single_sector(&entries[ole.path_index("Mini").unwrap()]).unwrap(), None);
+    }
+}
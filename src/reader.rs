@@ -0,0 +1,127 @@
+use crate::common::OleResult;
+use crate::ole::{Ole, ParseOptions};
+use std::fs;
+use std::io::{Cursor, Read, Seek};
+use std::path::Path;
+
+/// opens and parses files with the `ParseOptions` knobs set fluently, in one place
+///
+/// ```
+/// use rust_ole::reader::OleReader;
+///
+/// let ole = OleReader::new().strict(true).max_chain_len(1 << 20).open("./abcd.doc").unwrap();
+/// assert!(ole.options.strict);
+/// assert_eq!(ole.read_stream("WordDocument").unwrap().len(), 6144);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct OleReader {
+    options: ParseOptions,
+    lazy: bool,
+}
+
+impl OleReader {
+    /// a reader with the default options; `Ole::open` is this one made `lazy`
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// replace every option at once
+    pub fn options(mut self, options: ParseOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// see `ParseOptions::strict`
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.options.strict = strict;
+        self
+    }
+
+    /// see `ParseOptions::honor_header_cutoff`
+    pub fn honor_header_cutoff(mut self, honor: bool) -> Self {
+        self.options.honor_header_cutoff = honor;
+        self
+    }
+
+    /// see `ParseOptions::max_depth`
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.options.max_depth = depth;
+        self
+    }
+
+    /// see `ParseOptions::max_chain_len`
+    pub fn max_chain_len(mut self, len: usize) -> Self {
+        self.options.max_chain_len = len;
+        self
+    }
+
+    /// parse only the header and DIFAT when opening, the FAT, mini FAT and directory
+    /// follow on the first read or listing, see `Ole::open`
+    pub fn lazy(mut self, lazy: bool) -> Self {
+        self.lazy = lazy;
+        self
+    }
+
+    /// open and parse the file at `path`
+    pub fn open<P: AsRef<Path>>(&self, path: P) -> OleResult<Ole> {
+        self.open_slice(&fs::read(path)?)
+    }
+
+    /// parse a file already in memory
+    pub fn open_slice(&self, buf: &[u8]) -> OleResult<Ole> {
+        self.open_reader(Cursor::new(buf))
+    }
+
+    /// parse a file starting at the reader's current position, see `Ole::from_reader`
+    pub fn open_reader<R: Read + Seek>(&self, reader: R) -> OleResult<Ole> {
+        let mut ole = Ole::from_reader(reader)?;
+        if self.lazy {
+            ole.options = self.options.clone();
+            ole.defer_tables()?;
+        } else {
+            ole.parse_with_options(self.options.clone())?;
+        }
+        Ok(ole)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::OleReader;
+    use crate::common::{OleError, ParseWarning};
+    use crate::ole::ParseOptions;
+    use crate::test_util::*;
+
+    #[test]
+    fn configure_through_builder() {
+        let bytes = build(vec![
+            stream("WordDocument", pattern(6144, 7)),
+            storage("Nested", vec![stream("Small", pattern(100, 1))]),
+        ]);
+        let ole = OleReader::new().strict(true).max_depth(4).open_slice(&bytes).unwrap();
+        assert_eq!(ole.options, ParseOptions { strict: true, max_depth: 4, ..ParseOptions::default() });
+        assert_eq!(ole.read_stream("/Nested/Small").unwrap(), pattern(100, 1));
+
+        // WordDocument's 12 sectors are past the cap, the rest still reads
+        let ole = OleReader::new().max_chain_len(8).open_slice(&bytes).unwrap();
+        assert!(matches!(&ole.warnings[..], [ParseWarning::BrokenChain(name, _)] if name == "WordDocument"));
+        assert!(matches!(ole.read_stream("WordDocument"), Err(OleError::NoChain { .. })));
+        assert_eq!(ole.read_stream("/Nested/Small").unwrap(), pattern(100, 1));
+
+        let ole = OleReader::new().honor_header_cutoff(false).options(ParseOptions::default()).open("./abcd.doc").unwrap();
+        assert_eq!(ole.options, ParseOptions::default());
+    }
+
+    #[test]
+    fn lazy_keeps_options() {
+        let bytes = build(vec![stream("WordDocument", pattern(6144, 7))]);
+        let ole = OleReader::new().lazy(true).max_chain_len(8).open_slice(&bytes).unwrap();
+        assert!(ole.entries.is_none());
+        assert_eq!(ole.options.max_chain_len, 8);
+        // the deferred parse goes by the reader's options
+        assert!(matches!(ole.read_stream("WordDocument"), Err(OleError::NoChain { .. })));
+
+        let ole = OleReader::new().lazy(true).open_slice(&bytes).unwrap();
+        assert_eq!(ole.read_stream("WordDocument").unwrap(), pattern(6144, 7));
+    }
+}
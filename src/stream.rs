@@ -0,0 +1,300 @@
+use crate::common::{OleError, OleResult};
+use crate::directory::Entry;
+use crate::ole::{Ole, Segment, StreamLocator};
+use std::io::{BufRead, Read, Seek, SeekFrom};
+
+/// a cursor over one stream, reading straight out of the sectors held by `Ole`
+///
+/// readers only borrow the file, so any number of them can be open at once over the
+/// same or different streams, each keeping its own position; the borrow also means
+/// the file can't be edited while a reader is alive
+///
+/// like `BufReader`, the sector under the cursor is kept between calls and small
+/// reads are served from it until it runs out; it borrows from `body`, so nothing
+/// gets copied twice
+pub struct StreamReader<'a> {
+    ole: &'a Ole,
+    segments: Vec<Segment>,
+    /// stream offset each segment starts at
+    starts: Vec<u64>,
+    size: u64,
+    pos: u64,
+    /// index of the segment in `buf`, `None` before the first fetch
+    current: Option<usize>,
+    buf: &'a [u8],
+    /// sectors looked up so far
+    fetches: usize,
+}
+
+impl<'a> StreamReader<'a> {
+    pub(crate) fn new(ole: &'a Ole, segments: Vec<Segment>) -> Self {
+        let mut starts = Vec::with_capacity(segments.len());
+        let mut size = 0;
+        for segment in &segments {
+            starts.push(size);
+            size += segment.len as u64;
+        }
+        Self { ole, segments, starts, size, pos: 0, current: None, buf: &[], fetches: 0 }
+    }
+
+    /// length of the stream in bytes
+    pub fn len(&self) -> u64 {
+        self.size
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+
+    pub fn read_u16_le(&mut self) -> std::io::Result<u16> {
+        let mut buf = [0; 2];
+        self.read_exact(&mut buf)?;
+        Ok(u16::from_le_bytes(buf))
+    }
+
+    pub fn read_u32_le(&mut self) -> std::io::Result<u32> {
+        let mut buf = [0; 4];
+        self.read_exact(&mut buf)?;
+        Ok(u32::from_le_bytes(buf))
+    }
+
+    pub fn read_u64_le(&mut self) -> std::io::Result<u64> {
+        let mut buf = [0; 8];
+        self.read_exact(&mut buf)?;
+        Ok(u64::from_le_bytes(buf))
+    }
+
+    /// point `buf` at the segment holding `pos`, unless it's there already
+    fn fetch(&mut self) -> std::io::Result<()> {
+        if self.pos >= self.size {
+            return Ok(());
+        }
+        let idx = self.starts.partition_point(|v| *v <= self.pos) - 1;
+        if self.current == Some(idx) {
+            return Ok(());
+        }
+        let segment = &self.segments[idx];
+        self.buf = self.ole.body.get(segment.sector)
+            .and_then(|v| v.get(segment.start..segment.start + segment.len))
+            .ok_or(std::io::ErrorKind::UnexpectedEof)?;
+        self.current = Some(idx);
+        self.fetches += 1;
+        Ok(())
+    }
+}
+
+impl Read for StreamReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let data = self.fill_buf()?;
+        let len = std::cmp::min(buf.len(), data.len());
+        buf[..len].copy_from_slice(&data[..len]);
+        self.consume(len);
+        Ok(len)
+    }
+}
+
+impl BufRead for StreamReader<'_> {
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        self.fetch()?;
+        match self.current {
+            Some(idx) if self.pos < self.size => Ok(&self.buf[(self.pos - self.starts[idx]) as usize..]),
+            _ => Ok(&[]),
+        }
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos += amt as u64;
+    }
+}
+
+impl Seek for StreamReader<'_> {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        let target = match pos {
+            SeekFrom::Start(v) => Some(v),
+            SeekFrom::End(v) => self.size.checked_add_signed(v),
+            SeekFrom::Current(v) => self.pos.checked_add_signed(v),
+        };
+        self.pos = target.ok_or(std::io::ErrorKind::InvalidInput)?;
+        Ok(self.pos)
+    }
+}
+
+impl Ole {
+    /// open a reader over a stream given by entry, index or path
+    ///
+    /// each `read` hands out at most one sector or mini sector, mini streams being
+    /// picked by the same cutoff as `read`; can be called repeatedly, every reader is
+    /// independent of the others
+    pub fn open_stream<'a, L: StreamLocator<'a>>(&'a self, loc: L) -> OleResult<StreamReader<'a>> {
+        let entry = loc.locate(self)?;
+        Ok(StreamReader::new(self, self.stream_segments(entry)?))
+    }
+
+    /// one reader over several streams back to back, for data split across streams;
+    /// seeking works across the joins as well
+    pub fn open_concatenated(&self, paths: &[&str]) -> OleResult<StreamReader<'_>> {
+        let mut segments = vec![];
+        for path in paths {
+            segments.extend(self.stream_segments(path.locate(self)?)?);
+        }
+        Ok(StreamReader::new(self, segments))
+    }
+
+    /// call `f` with each piece of a stream in order, straight out of the sectors and
+    /// trimmed to `stream_size`, so the whole stream is never buffered
+    pub fn for_each_sector<F: FnMut(&[u8])>(&self, entry: &Entry, mut f: F) -> OleResult<()> {
+        for segment in self.stream_segments(entry)? {
+            let data = self.body.get(segment.sector)
+                .and_then(|v| v.get(segment.start..segment.start + segment.len))
+                .ok_or(OleError::InvalidEntrySize)?;
+            f(data);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_util::*;
+    use std::io::{BufRead, Read, Seek, SeekFrom};
+
+    #[test]
+    fn open_stream_readers_are_independent() {
+        let ole = open(&build(vec![
+            stream("WordDocument", pattern(6144, 7)),
+            stream("Small", pattern(300, 1)),
+        ]));
+        let mut first = ole.open_stream("WordDocument").unwrap();
+        let mut second = ole.open_stream("WordDocument").unwrap();
+        let mut small = ole.open_stream("Small").unwrap();
+
+        let mut buf = vec![0; 1000];
+        first.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, pattern(6144, 7)[..1000]);
+
+        let mut rest = vec![];
+        second.read_to_end(&mut rest).unwrap();
+        assert_eq!(rest, pattern(6144, 7));
+
+        first.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, pattern(6144, 7)[1000..2000]);
+
+        small.seek(SeekFrom::Start(250)).unwrap();
+        rest.clear();
+        small.read_to_end(&mut rest).unwrap();
+        assert_eq!(rest, pattern(300, 1)[250..]);
+        assert_eq!(small.len(), 300);
+    }
+
+    #[test]
+    fn open_stream_by_entry_reads_sector_by_sector() {
+        let ole = open(&build(vec![
+            stream("WordDocument", pattern(6000, 7)),
+            stream("Small", pattern(300, 1)),
+        ]));
+        let entries = ole.entries.as_ref().unwrap();
+        for (name, expected, piece) in [("WordDocument", pattern(6000, 7), 512), ("Small", pattern(300, 1), 64)] {
+            let entry = &entries[ole.path_index(name).unwrap()];
+            let mut reader = ole.open_stream(entry).unwrap();
+            let mut buf = vec![0; 4096];
+            let mut data = Vec::<u8>::new();
+            loop {
+                let n = reader.read(&mut buf).unwrap();
+                if n == 0 {
+                    break;
+                }
+                assert!(n <= piece);
+                data.extend(&buf[..n]);
+            }
+            assert_eq!(data, expected);
+
+            let mut copied = vec![];
+            std::io::copy(&mut ole.open_stream(entry).unwrap(), &mut copied).unwrap();
+            assert_eq!(copied, expected);
+        }
+    }
+
+    #[test]
+    fn byte_reads_fetch_each_sector_once() {
+        let ole = open(&build(vec![
+            stream("WordDocument", pattern(6000, 7)),
+            stream("Small", pattern(300, 1)),
+        ]));
+        for (name, expected, sectors) in [("WordDocument", pattern(6000, 7), 12), ("Small", pattern(300, 1), 5)] {
+            let mut reader = ole.open_stream(name).unwrap();
+            let mut data = vec![];
+            let mut byte = [0; 1];
+            while reader.read(&mut byte).unwrap() == 1 {
+                data.push(byte[0]);
+            }
+            assert_eq!(data, expected);
+            assert_eq!(reader.fetches, sectors);
+        }
+
+        let mut reader = ole.open_stream("WordDocument").unwrap();
+        let mut line = vec![];
+        reader.seek(SeekFrom::Start(600)).unwrap();
+        reader.read_until(pattern(6000, 7)[700], &mut line).unwrap();
+        assert_eq!(line, pattern(6000, 7)[600..=700]);
+        reader.seek(SeekFrom::Start(1000)).unwrap();
+        assert_eq!(reader.fill_buf().unwrap(), &pattern(6000, 7)[1000..1024]);
+        assert_eq!(reader.fetches, 1);
+    }
+
+    #[test]
+    fn open_concatenated() {
+        let ole = open(&build(vec![
+            stream("WordDocument", pattern(6000, 7)),
+            stream("0Table", pattern(300, 1)),
+            stream("1Table", pattern(5000, 2)),
+        ]));
+        let mut reader = ole.open_concatenated(&["1Table", "0Table", "WordDocument"]).unwrap();
+        let mut joined = vec![];
+        reader.read_to_end(&mut joined).unwrap();
+        assert_eq!(joined, [pattern(5000, 2), pattern(300, 1), pattern(6000, 7)].concat());
+
+        reader.seek(SeekFrom::Start(4990)).unwrap();
+        let mut buf = vec![0; 20];
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, joined[4990..5010]);
+
+        assert!(ole.open_concatenated(&["0Table", "Missing"]).is_err());
+    }
+
+    #[test]
+    fn for_each_sector() {
+        let ole = open(&build(vec![
+            stream("WordDocument", pattern(6000, 7)),
+            stream("Small", pattern(300, 1)),
+        ]));
+        for (name, expected) in [("WordDocument", pattern(6000, 7)), ("Small", pattern(300, 1))] {
+            let entry = &ole.entries.as_ref().unwrap()[ole.path_index(name).unwrap()];
+            let mut pieces = vec![];
+            ole.for_each_sector(entry, |v| pieces.push(v.to_vec())).unwrap();
+            assert_eq!(pieces.concat(), expected);
+            assert_eq!(pieces.concat(), ole.read_cow(entry).unwrap().as_ref());
+        }
+
+        let entry = &ole.entries.as_ref().unwrap()[1];
+        let mut lens = vec![];
+        ole.for_each_sector(entry, |v| lens.push(v.len())).unwrap();
+        assert_eq!(lens.len(), 12);
+        assert_eq!(lens[11], 6000 - 11 * 512);
+        assert_eq!(ole.read(entry).unwrap(), pattern(6000, 7));
+    }
+
+    #[test]
+    fn read_le_integers() {
+        let mut data = vec![];
+        data.extend(0x1234u16.to_le_bytes());
+        data.extend(0xDEADBEEFu32.to_le_bytes());
+        data.extend(0x0102030405060708u64.to_le_bytes());
+        let ole = open(&build(vec![stream("Fields", data)]));
+
+        let mut reader = ole.open_stream("Fields").unwrap();
+        assert_eq!(reader.read_u16_le().unwrap(), 0x1234);
+        assert_eq!(reader.read_u32_le().unwrap(), 0xDEADBEEF);
+        assert_eq!(reader.read_u64_le().unwrap(), 0x0102030405060708);
+        assert!(reader.read_u16_le().is_err());
+    }
+}
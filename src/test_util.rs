@@ -0,0 +1,229 @@
+#![allow(dead_code)]
+
+use crate::common::SectorType;
+use crate::directory::{compare_names, Color, Entry, ObjectType};
+use crate::ole::Ole;
+use binrw::BinWrite;
+use std::io::Cursor;
+
+/// hand-assembled compound files for tests
+///
+/// layout is always: FAT sectors, directory sectors, mini FAT sectors,
+/// mini stream container sectors, then regular stream sectors in order
+pub(crate) enum Node {
+    Storage(String, Vec<Node>),
+    Stream(String, Vec<u8>),
+}
+
+pub(crate) fn storage(name: &str, children: Vec<Node>) -> Node {
+    Node::Storage(name.to_string(), children)
+}
+
+pub(crate) fn stream(name: &str, data: Vec<u8>) -> Node {
+    Node::Stream(name.to_string(), data)
+}
+
+/// deterministic non-trivial payload
+pub(crate) fn pattern(len: usize, seed: u8) -> Vec<u8> {
+    (0..len).map(|i| (i as u8).wrapping_mul(31).wrapping_add(seed)).collect()
+}
+
+pub(crate) fn build(nodes: Vec<Node>) -> Vec<u8> {
+    build_with(9, 4096, nodes)
+}
+
+pub(crate) fn build_v4(nodes: Vec<Node>) -> Vec<u8> {
+    build_with(12, 4096, nodes)
+}
+
+/// a version 3 file laid out and labelled with a non-standard mini stream cutoff
+pub(crate) fn build_with_cutoff(cutoff: usize, nodes: Vec<Node>) -> Vec<u8> {
+    build_with(9, cutoff, nodes)
+}
+
+/// byte offset of a body sector inside a file produced by `build`
+pub(crate) fn sector_offset(index: usize) -> usize {
+    512 + index * 512
+}
+
+pub(crate) fn open(bytes: &[u8]) -> Ole {
+    let mut ole = open_unparsed(bytes);
+    ole.parse().unwrap();
+    ole
+}
+
+pub(crate) fn open_unparsed(bytes: &[u8]) -> Ole {
+    Ole::from_slice(bytes).unwrap()
+}
+
+fn add_children(entries: &mut Vec<Entry>, payloads: &mut Vec<(usize, Vec<u8>)>, parent: usize, children: Vec<Node>) {
+    let mut ids = vec![];
+    let mut storages = vec![];
+    for child in children {
+        let idx = entries.len();
+        match child {
+            Node::Storage(name, nested) => {
+                entries.push(Entry::new(&name, ObjectType::Storage));
+                storages.push((idx, nested));
+            }
+            Node::Stream(name, data) => {
+                entries.push(Entry::new(&name, ObjectType::Stream));
+                payloads.push((idx, data));
+            }
+        }
+        ids.push(idx);
+    }
+    ids.sort_by(|a, b| compare_names(&entries[*a].name(), &entries[*b].name()));
+    entries[parent].child_id = balance(entries, &ids);
+    for (idx, nested) in storages {
+        add_children(entries, payloads, idx, nested);
+    }
+}
+
+fn balance(entries: &mut Vec<Entry>, sorted: &[usize]) -> SectorType {
+    if sorted.is_empty() {
+        return SectorType::FreeSect;
+    }
+    let mid = sorted.len() / 2;
+    let left = balance(entries, &sorted[..mid]);
+    let right = balance(entries, &sorted[mid + 1..]);
+    let entry = &mut entries[sorted[mid]];
+    entry.left_sibling_id = left;
+    entry.right_sibling_id = right;
+    SectorType::RegularSect(sorted[mid] as u32)
+}
+
+/// black nodes on every path from `node` down, `None` when they differ or a red
+/// node has a red child
+pub(crate) fn black_height(entries: &[Entry], node: &SectorType) -> Option<usize> {
+    let SectorType::RegularSect(v) = node else { return Some(1) };
+    let entry = &entries[*v as usize];
+    let left = black_height(entries, &entry.left_sibling_id)?;
+    let right = black_height(entries, &entry.right_sibling_id)?;
+    let red_child = [&entry.left_sibling_id, &entry.right_sibling_id].into_iter()
+        .any(|v| matches!(v, SectorType::RegularSect(i) if entries[*i as usize].color == Color::Red));
+    if left != right || (entry.color == Color::Red && red_child) {
+        return None;
+    }
+    Some(left + usize::from(entry.color == Color::Black))
+}
+
+fn link(table: &mut [u32], start: usize, count: usize) {
+    for i in 0..count {
+        table[start + i] = if i + 1 == count { 0xFFFFFFFE } else { (start + i + 1) as u32 };
+    }
+}
+
+fn build_with(shift: u16, cutoff: usize, nodes: Vec<Node>) -> Vec<u8> {
+    let sector_size = 1usize << shift;
+    let per_sector = sector_size / 4;
+    let entries_per_sector = sector_size / 128;
+
+    let mut entries = vec![Entry::new("Root Entry", ObjectType::RootStorage)];
+    let mut payloads = vec![];
+    add_children(&mut entries, &mut payloads, 0, nodes);
+    while entries.len() % entries_per_sector != 0 {
+        entries.push(Entry::unused());
+    }
+
+    let mini_sectors = payloads.iter()
+        .filter(|(_, d)| d.len() < cutoff)
+        .map(|(_, d)| d.len().div_ceil(64))
+        .sum::<usize>();
+    let regular_sectors = payloads.iter()
+        .filter(|(_, d)| d.len() >= cutoff)
+        .map(|(_, d)| d.len().div_ceil(sector_size))
+        .sum::<usize>();
+    let directory_sectors = entries.len() / entries_per_sector;
+    let mini_fat_sectors = mini_sectors.div_ceil(per_sector);
+    let container_sectors = (mini_sectors * 64).div_ceil(sector_size);
+    let rest = directory_sectors + mini_fat_sectors + container_sectors + regular_sectors;
+    let mut fat_sectors = 1;
+    while fat_sectors * per_sector < fat_sectors + rest {
+        fat_sectors += 1;
+    }
+    assert!(fat_sectors <= 109, "fixture too large for header DIFAT");
+
+    let first_directory = fat_sectors;
+    let first_mini_fat = first_directory + directory_sectors;
+    let first_container = first_mini_fat + mini_fat_sectors;
+    let mut next_regular = first_container + container_sectors;
+
+    let mut fat = vec![0xFFFFFFFFu32; fat_sectors * per_sector];
+    for v in fat.iter_mut().take(fat_sectors) {
+        *v = 0xFFFFFFFD;
+    }
+    link(&mut fat, first_directory, directory_sectors);
+    link(&mut fat, first_mini_fat, mini_fat_sectors);
+    link(&mut fat, first_container, container_sectors);
+
+    let mut mini_fat = vec![0xFFFFFFFFu32; mini_fat_sectors * per_sector];
+    let mut container = vec![0u8; container_sectors * sector_size];
+    let mut regular = vec![];
+    let mut next_mini = 0usize;
+    for (idx, data) in &payloads {
+        let entry = &mut entries[*idx];
+        entry.stream_size = data.len() as u64;
+        if data.is_empty() {
+            entry.starting_sector_location = SectorType::EndOfChain;
+        } else if data.len() < cutoff {
+            let count = data.len().div_ceil(64);
+            link(&mut mini_fat, next_mini, count);
+            container[next_mini * 64..next_mini * 64 + data.len()].copy_from_slice(data);
+            entry.starting_sector_location = SectorType::RegularSect(next_mini as u32);
+            next_mini += count;
+        } else {
+            let count = data.len().div_ceil(sector_size);
+            link(&mut fat, next_regular, count);
+            let mut padded = data.clone();
+            padded.resize(count * sector_size, 0);
+            regular.extend(padded);
+            entry.starting_sector_location = SectorType::RegularSect(next_regular as u32);
+            next_regular += count;
+        }
+    }
+    entries[0].stream_size = (mini_sectors * 64) as u64;
+    entries[0].starting_sector_location = if container_sectors > 0 {
+        SectorType::RegularSect(first_container as u32)
+    } else {
+        SectorType::EndOfChain
+    };
+
+    let mut out = vec![];
+    out.extend(0xE11AB1A1E011CFD0u64.to_le_bytes());
+    out.extend([0u8; 16]);
+    out.extend(0x003Eu16.to_le_bytes());
+    out.extend((if shift == 9 { 3u16 } else { 4u16 }).to_le_bytes());
+    out.extend(0xFFFEu16.to_le_bytes());
+    out.extend(shift.to_le_bytes());
+    out.extend(6u16.to_le_bytes());
+    out.extend([0u8; 6]);
+    out.extend((if shift == 9 { 0 } else { directory_sectors as u32 }).to_le_bytes());
+    out.extend((fat_sectors as u32).to_le_bytes());
+    out.extend((first_directory as u32).to_le_bytes());
+    out.extend(0u32.to_le_bytes());
+    out.extend((cutoff as u32).to_le_bytes());
+    out.extend((if mini_fat_sectors > 0 { first_mini_fat as u32 } else { 0xFFFFFFFE }).to_le_bytes());
+    out.extend((mini_fat_sectors as u32).to_le_bytes());
+    out.extend(0xFFFFFFFEu32.to_le_bytes());
+    out.extend(0u32.to_le_bytes());
+    for i in 0..109 {
+        out.extend((if i < fat_sectors { i as u32 } else { 0xFFFFFFFF }).to_le_bytes());
+    }
+    out.resize(sector_size, 0);
+
+    for v in fat {
+        out.extend(v.to_le_bytes());
+    }
+    let mut cursor = Cursor::new(vec![]);
+    for entry in &entries {
+        entry.write_le(&mut cursor).unwrap();
+    }
+    out.extend(cursor.into_inner());
+    for v in mini_fat {
+        out.extend(v.to_le_bytes());
+    }
+    out.extend(container);
+    out.extend(regular);
+    out
+}
@@ -0,0 +1,567 @@
+use crate::common::{OleError, OleResult, SectorType};
+use crate::directory::{Color, Entry, ObjectType};
+use crate::ole::Ole;
+use std::collections::{BTreeMap, HashSet};
+
+/// walking the storage hierarchy: every storage's children hang off its `child_id`
+/// as a red-black tree linked through the sibling ids
+impl Ole {
+    /// every entry reachable from the root with its `/`-separated path, the root
+    /// itself being `/`
+    ///
+    /// siblings come in tree order and a storage precedes its children; ids that
+    /// point outside the directory or back at an entry already seen are not followed,
+    /// storages nested deeper than `options.max_depth` fail with `NestingTooDeep`
+    pub(crate) fn entry_paths(&self) -> OleResult<Vec<(String, usize)>> {
+        self.paths_within(self.options.max_depth)
+    }
+
+    fn paths_within(&self, max_depth: usize) -> OleResult<Vec<(String, usize)>> {
+        Ok(self.nodes_within(max_depth)?.into_iter().map(|(path, idx, _)| (path, idx)).collect())
+    }
+
+    /// the walk behind `paths_within`, also giving each entry's depth, 0 for the root
+    fn nodes_within(&self, max_depth: usize) -> OleResult<Vec<(String, usize, usize)>> {
+        let Ok(entries) = self.entries_ref() else {
+            return Ok(vec![]);
+        };
+        if entries.is_empty() {
+            return Ok(vec![]);
+        }
+        let mut visited = vec![false; entries.len()];
+        visited[0] = true;
+        let mut result = vec![("/".to_string(), 0, 0)];
+
+        // an explicit stack instead of recursion, so a degenerate sibling tree or a
+        // deep nesting can't exhaust the call stack; a node's right subtree is pushed
+        // before the node itself, so the node and its children come out first
+        let mut stack = vec![Step::Visit(entries[0].child_id.clone(), String::new(), 0)];
+        while let Some(step) = stack.pop() {
+            match step {
+                Step::Visit(node, parent, depth) => {
+                    let SectorType::RegularSect(v) = node else { continue };
+                    let idx = v as usize;
+                    match visited.get(idx) {
+                        Some(false) => visited[idx] = true,
+                        _ => continue,
+                    }
+                    let entry = &entries[idx];
+                    stack.push(Step::Visit(entry.right_sibling_id.clone(), parent.clone(), depth));
+                    stack.push(Step::Emit(idx, parent.clone(), depth));
+                    stack.push(Step::Visit(entry.left_sibling_id.clone(), parent, depth));
+                }
+                Step::Emit(idx, parent, depth) => {
+                    let entry = &entries[idx];
+                    let path = format!("{}/{}", parent, entry.name());
+                    result.push((path.clone(), idx, depth + 1));
+                    if entry.object_type == ObjectType::Storage {
+                        if depth + 1 > max_depth {
+                            return Err(OleError::NestingTooDeep(path));
+                        }
+                        stack.push(Step::Visit(entry.child_id.clone(), path, depth + 1));
+                    }
+                }
+            }
+        }
+        Ok(result)
+    }
+
+    /// every entry reachable from the root with its path, see `entry_paths`
+    pub fn walk(&self) -> OleResult<Vec<(String, &Entry)>> {
+        let entries = self.entries_ref()?;
+        Ok(self.entry_paths()?.into_iter().map(|(path, idx)| (path, &entries[idx])).collect())
+    }
+
+    /// the storage hierarchy as a tree rooted at the root entry, children in tree
+    /// order; guarded like `entry_paths`, so a looping directory still ends
+    pub fn tree(&self) -> OleResult<StorageNode> {
+        self.entries_ref()?;
+        // pre-order with depths, so a node's parent is always the last open node one
+        // level up
+        let mut open: Vec<StorageNode> = vec![];
+        for (_, index, depth) in self.nodes_within(self.options.max_depth)? {
+            while open.len() > depth {
+                close(&mut open);
+            }
+            open.push(StorageNode { index, children: vec![] });
+        }
+        while open.len() > 1 {
+            close(&mut open);
+        }
+        open.pop().ok_or(OleError::InvalidRootEntry)
+    }
+
+    /// how deeply storages nest, 0 when the root holds only streams
+    ///
+    /// the walk ignores `options.max_depth`, so this tells how high the limit has to
+    /// be for a file; fails with `NotParsed` before the directory is read
+    pub fn directory_depth(&self) -> OleResult<usize> {
+        let entries = self.entries_ref()?;
+        Ok(self.nodes_within(usize::MAX)?.into_iter()
+            .filter(|(_, idx, _)| entries[*idx].object_type == ObjectType::Storage)
+            .map(|(_, _, depth)| depth)
+            .max()
+            .unwrap_or(0))
+    }
+
+    /// storage entries, including the root, with their paths
+    ///
+    /// yields nothing when the tree is nested deeper than `options.max_depth`
+    pub fn iter_storages(&self) -> impl Iterator<Item = (String, &Entry)> {
+        self.entry_paths().unwrap_or_default().into_iter().filter_map(|(path, idx)| {
+            let entry = &self.entries_ref().ok()?[idx];
+            match entry.object_type {
+                ObjectType::Storage | ObjectType::RootStorage => Some((path, entry)),
+                _ => None,
+            }
+        })
+    }
+
+    /// storages, including the root, as (path, depth, entry) with every storage ahead
+    /// of the ones nested in it, for building a folder tree; the root is depth 0
+    ///
+    /// yields nothing when the tree is nested deeper than `options.max_depth`
+    pub fn iter_storages_dfs(&self) -> impl Iterator<Item = (String, usize, &Entry)> {
+        self.nodes_within(self.options.max_depth).unwrap_or_default().into_iter().filter_map(|(path, idx, depth)| {
+            let entry = &self.entries_ref().ok()?[idx];
+            matches!(entry.object_type, ObjectType::Storage | ObjectType::RootStorage).then_some((path, depth, entry))
+        })
+    }
+
+    /// stream entries with their full paths, storages and the root are skipped; a
+    /// child or sibling id outside the directory just ends that branch
+    ///
+    /// yields nothing when the tree is nested deeper than `options.max_depth`
+    pub fn iter_streams(&self) -> impl Iterator<Item = (String, &Entry)> {
+        self.entry_paths().unwrap_or_default().into_iter().filter_map(|(path, idx)| {
+            let entry = &self.entries_ref().ok()?[idx];
+            (entry.object_type == ObjectType::Stream).then_some((path, entry))
+        })
+    }
+
+    /// owned summaries of every stream, in the order of `iter_streams`, for handing
+    /// stream metadata on without borrowing the file
+    pub fn stream_descriptors(&self) -> Vec<StreamDescriptor> {
+        self.entry_paths().unwrap_or_default().into_iter().filter_map(|(path, idx)| {
+            let entry = &self.entries_ref().ok()?[idx];
+            (entry.object_type == ObjectType::Stream).then(|| StreamDescriptor {
+                id: idx as u32,
+                path,
+                size: entry.effective_size(&self.version),
+                is_mini: self.in_mini_stream(entry),
+                clsid: entry.clsid,
+            })
+        }).collect()
+    }
+
+    /// the directory as a DOT graph: one node per allocated entry filled with its
+    /// red-black color, and `left`, `right` and `child` edges exactly as stored, so
+    /// a broken tree shows up as stray or looping edges
+    pub fn to_graphviz(&self) -> String {
+        let mut out = String::from("digraph directory {\n    node [style=filled, fontcolor=white];\n");
+        for (i, entry) in self.entries_ref().into_iter().flatten().enumerate() {
+            if entry.object_type == ObjectType::Unknown {
+                continue;
+            }
+            let name = entry.name().replace('\\', "\\\\").replace('"', "\\\"");
+            let color = if entry.color == Color::Red { "red" } else { "black" };
+            out.push_str(&format!("    n{} [label=\"{}: {}\", fillcolor={}];\n", i, i, name, color));
+            for (label, link) in [("left", &entry.left_sibling_id), ("right", &entry.right_sibling_id), ("child", &entry.child_id)] {
+                if let SectorType::RegularSect(v) = link {
+                    out.push_str(&format!("    n{} -> n{} [label={}];\n", i, v, label));
+                }
+            }
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    /// every stream keyed by its full path, which is unique within the file, unlike
+    /// a bare name
+    pub fn read_all_streams(&self) -> OleResult<BTreeMap<String, Vec<u8>>> {
+        let entries = self.entries_ref()?;
+        let mut result = BTreeMap::new();
+        for (path, idx) in self.entry_paths()? {
+            let entry = &entries[idx];
+            if entry.object_type == ObjectType::Stream {
+                result.insert(path, self.read_contents(entry)?);
+            }
+        }
+        Ok(result)
+    }
+
+    /// a relative file path for every stream, for extracting to disk, keyed by the
+    /// stream's full path
+    ///
+    /// each component is sanitized, and where that makes two of them collide,
+    /// ignoring case, the later one in tree order gets a `~1`, `~2`, ... suffix, so
+    /// the same file always maps to the same names
+    pub fn sanitized_paths(&self) -> OleResult<BTreeMap<String, String>> {
+        let entries = self.entries_ref()?;
+        // the sanitized path of every storage seen so far, by full path
+        let mut renamed = BTreeMap::from([("/".to_string(), String::new())]);
+        let mut taken = HashSet::new();
+        let mut result = BTreeMap::new();
+        for (path, idx) in self.entry_paths()?.into_iter().skip(1) {
+            let entry = &entries[idx];
+            let (parent, _) = path.rsplit_once('/').unwrap_or_default();
+            let parent = renamed.get(if parent.is_empty() { "/" } else { parent }).cloned().unwrap_or_default();
+            let base = format!("{}{}", parent, sanitize(&entry.name()));
+            let mut unique = base.clone();
+            let mut n = 0;
+            while !taken.insert(unique.to_lowercase()) {
+                n += 1;
+                unique = format!("{}~{}", base, n);
+            }
+            match entry.object_type {
+                ObjectType::Storage => {
+                    renamed.insert(path, format!("{}/", unique));
+                }
+                ObjectType::Stream => {
+                    result.insert(path, unique);
+                }
+                _ => {}
+            }
+        }
+        Ok(result)
+    }
+
+    /// read every stream whose path matches `pattern`, where `*` stands for any run of
+    /// characters, `/` included, and `?` for exactly one; the leading `/` is optional
+    pub fn read_glob(&self, pattern: &str) -> OleResult<Vec<(String, Vec<u8>)>> {
+        let pattern = pattern.trim_start_matches('/').chars().collect::<Vec<char>>();
+        let entries = self.entries_ref()?;
+        let mut result = vec![];
+        for (path, idx) in self.entry_paths()? {
+            let entry = &entries[idx];
+            let name = path.trim_start_matches('/').chars().collect::<Vec<char>>();
+            if entry.object_type == ObjectType::Stream && glob_match(&pattern, &name) {
+                result.push((path, self.read_contents(entry)?));
+            }
+        }
+        Ok(result)
+    }
+}
+
+/// an entry of the storage hierarchy, see `Ole::tree`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StorageNode {
+    /// index of the entry in the directory
+    pub index: usize,
+    /// entries directly under this one, empty for streams
+    pub children: Vec<StorageNode>,
+}
+
+/// a stream's metadata, see `Ole::stream_descriptors`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StreamDescriptor {
+    /// index of the entry in the directory
+    pub id: u32,
+    /// full path of the stream
+    pub path: String,
+    /// size in bytes
+    pub size: u64,
+    /// whether the data lives in the mini stream
+    pub is_mini: bool,
+    pub clsid: [u8; 16],
+}
+
+/// attach the innermost open node to its parent
+fn close(open: &mut Vec<StorageNode>) {
+    if let Some(node) = open.pop() {
+        if let Some(parent) = open.last_mut() {
+            parent.children.push(node);
+        }
+    }
+}
+
+/// a file name safe on common filesystems: control characters and the ones
+/// Windows reserves become `_`
+fn sanitize(name: &str) -> String {
+    name.chars().map(|c| if c.is_control() || "\\/:*?\"<>|".contains(c) { '_' } else { c }).collect()
+}
+
+/// wildcard match, backtracking only to the most recent `*`
+fn glob_match(pattern: &[char], text: &[char]) -> bool {
+    let (mut p, mut t) = (0, 0);
+    let mut star = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, t));
+                p += 1;
+            }
+            Some(c) if *c == '?' || *c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match star {
+                Some((sp, st)) => {
+                    p = sp + 1;
+                    t = st + 1;
+                    star = Some((sp, st + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// pending work of the tree walk, `depth` being the nesting depth of the storage
+/// owning the sibling tree
+enum Step {
+    Visit(SectorType, String, usize),
+    Emit(usize, String, usize),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::StorageNode;
+    use crate::common::{OleError, SectorType};
+    use crate::directory::{Entry, ObjectType};
+    use crate::ole::Ole;
+    use crate::test_util::*;
+
+    #[test]
+    fn iter_storages() {
+        let ole = open(&build(vec![
+            stream("WordDocument", pattern(100, 7)),
+            storage("ObjectPool", vec![
+                storage("_1234", vec![stream("\u{1}Ole", pattern(20, 1))]),
+                storage("_5678", vec![]),
+            ]),
+            storage("Macros", vec![storage("VBA", vec![stream("dir", pattern(30, 2))])]),
+        ]));
+        let paths = ole.iter_storages().map(|(path, _)| path).collect::<Vec<String>>();
+        assert_eq!(paths, vec!["/", "/Macros", "/Macros/VBA", "/ObjectPool", "/ObjectPool/_1234", "/ObjectPool/_5678"]);
+        assert_eq!(ole.iter_storages().next().unwrap().1.name(), "Root Entry");
+    }
+
+    #[test]
+    fn iter_storages_dfs() {
+        let ole = open(&build(vec![
+            stream("WordDocument", pattern(100, 7)),
+            storage("ObjectPool", vec![
+                storage("_5678", vec![]),
+                storage("_1234", vec![storage("Inner", vec![stream("\u{1}Ole", pattern(20, 1))])]),
+            ]),
+            storage("Macros", vec![storage("VBA", vec![])]),
+        ]));
+        let found = ole.iter_storages_dfs().map(|(path, depth, _)| (path, depth)).collect::<Vec<(String, usize)>>();
+        assert_eq!(found, vec![
+            ("/".to_string(), 0),
+            ("/Macros".to_string(), 1),
+            ("/Macros/VBA".to_string(), 2),
+            ("/ObjectPool".to_string(), 1),
+            ("/ObjectPool/_1234".to_string(), 2),
+            ("/ObjectPool/_1234/Inner".to_string(), 3),
+            ("/ObjectPool/_5678".to_string(), 2),
+        ]);
+        for (i, (path, depth)) in found.iter().enumerate().skip(1) {
+            let (parent, _) = path.rsplit_once('/').unwrap();
+            let parent = if parent.is_empty() { "/" } else { parent };
+            assert!(found[..i].iter().any(|(p, d)| p == parent && d + 1 == *depth));
+        }
+    }
+
+    #[test]
+    fn iter_streams() {
+        let ole = open(&build(vec![
+            stream("WordDocument", pattern(100, 7)),
+            storage("ObjectPool", vec![storage("_1234", vec![stream("\u{1}Ole", pattern(20, 1))])]),
+            stream("1Table", pattern(30, 2)),
+        ]));
+        let paths = ole.iter_streams().map(|(path, _)| path).collect::<Vec<String>>();
+        assert_eq!(paths, vec!["/1Table", "/ObjectPool/_1234/\u{1}Ole", "/WordDocument"]);
+        assert!(ole.iter_streams().all(|(_, entry)| entry.object_type == ObjectType::Stream));
+    }
+
+    #[test]
+    fn tree() {
+        let mut ole = open(&build(vec![
+            stream("WordDocument", pattern(100, 7)),
+            storage("ObjectPool", vec![
+                storage("_1234", vec![stream("\u{1}Ole", pattern(20, 1))]),
+                storage("_5678", vec![]),
+            ]),
+            stream("1Table", pattern(30, 2)),
+        ]));
+        let names = |node: &StorageNode, ole: &Ole| -> Vec<String> {
+            let entries = ole.entries.as_ref().unwrap();
+            node.children.iter().map(|v| entries[v.index].name().into_owned()).collect()
+        };
+        let root = ole.tree().unwrap();
+        assert_eq!(root.index, 0);
+        assert_eq!(names(&root, &ole), vec!["1Table", "ObjectPool", "WordDocument"]);
+        let pool = &root.children[1];
+        assert_eq!(names(pool, &ole), vec!["_1234", "_5678"]);
+        assert_eq!(names(&pool.children[0], &ole), vec!["\u{1}Ole"]);
+        assert!(pool.children[1].children.is_empty());
+        assert!(root.children[0].children.is_empty());
+
+        // a storage whose child points back at the root is cut off instead of looping
+        let idx = ole.path_index("/ObjectPool/_5678").unwrap();
+        ole.entries.as_mut().unwrap()[idx].child_id = SectorType::RegularSect(0);
+        assert_eq!(ole.tree().unwrap(), root);
+    }
+
+    #[test]
+    fn iter_streams_skips_out_of_range_ids() {
+        let mut ole = open(&build(vec![
+            stream("A", pattern(10, 1)),
+            storage("B", vec![stream("Inner", pattern(10, 2))]),
+            stream("C", pattern(10, 3)),
+        ]));
+        let entries = ole.entries.as_mut().unwrap();
+        let len = entries.len() as u32;
+        let b = entries.iter().position(|v| v.name() == "B").unwrap();
+        let c = entries.iter().position(|v| v.name() == "C").unwrap();
+        entries[b].child_id = SectorType::RegularSect(len + 10);
+        entries[c].left_sibling_id = SectorType::RegularSect(u32::MAX - 6);
+        entries[c].right_sibling_id = SectorType::RegularSect(len);
+
+        let paths = ole.iter_streams().map(|(path, _)| path).collect::<Vec<String>>();
+        assert!(paths.contains(&"/C".to_string()));
+        assert!(!paths.contains(&"/B/Inner".to_string()));
+        assert!(ole.iter_streams().all(|(_, entry)| entry.object_type == ObjectType::Stream));
+    }
+
+    #[test]
+    fn stream_descriptors() {
+        let mut ole = open(&build(vec![
+            stream("WordDocument", pattern(6144, 7)),
+            storage("ObjectPool", vec![stream("\u{1}Ole", pattern(20, 1))]),
+        ]));
+        let idx = ole.path_index("/WordDocument").unwrap();
+        ole.entries.as_mut().unwrap()[idx].clsid = [7; 16];
+
+        let descriptors = ole.stream_descriptors();
+        assert_eq!(descriptors.iter().map(|v| v.path.as_str()).collect::<Vec<&str>>(), vec!["/ObjectPool/\u{1}Ole", "/WordDocument"]);
+        let entries = ole.entries.as_ref().unwrap();
+        for (descriptor, (path, entry)) in descriptors.iter().zip(ole.iter_streams()) {
+            let by_id = &entries[descriptor.id as usize];
+            assert_eq!(descriptor.path, path);
+            assert_eq!(by_id.name(), entry.name());
+            assert_eq!(descriptor.size, entry.effective_size(&ole.version));
+            assert_eq!(descriptor.clsid, entry.clsid);
+        }
+        assert!(descriptors[0].is_mini);
+        assert!(!descriptors[1].is_mini);
+        assert_eq!(descriptors[1].clsid, [7; 16]);
+    }
+
+    #[test]
+    fn directory_depth() {
+        let mut node = stream("Leaf", pattern(10, 1));
+        for i in 0..20 {
+            node = storage(&format!("Level{}", i), vec![node]);
+        }
+        let mut ole = open(&build(vec![node, stream("Top", pattern(10, 2))]));
+        assert_eq!(ole.directory_depth().unwrap(), 20);
+        assert_eq!(ole.walk().unwrap().len(), 23);
+
+        ole.options.max_depth = 19;
+        assert!(matches!(ole.walk(), Err(OleError::NestingTooDeep(_))));
+        assert_eq!(ole.iter_streams().count(), 0);
+        assert_eq!(ole.directory_depth().unwrap(), 20);
+
+        // a `/` inside a name is not a level
+        let ole = open(&build(vec![storage("a/b/c", vec![stream("d/e", pattern(10, 1))])]));
+        assert_eq!(ole.directory_depth().unwrap(), 1);
+        assert!(matches!(open_unparsed(&build(vec![])).directory_depth(), Err(OleError::NotParsed)));
+    }
+
+    #[test]
+    fn to_graphviz() {
+        let ole = open(&build(vec![
+            stream("A", pattern(10, 1)),
+            stream("B", pattern(10, 2)),
+            stream("C\"", pattern(10, 3)),
+        ]));
+        let dot = ole.to_graphviz();
+        assert!(dot.starts_with("digraph directory {"));
+        assert!(dot.contains("n0 [label=\"0: Root Entry\", fillcolor=black];"));
+        assert!(dot.contains("n0 -> n2 [label=child];"));
+        assert!(dot.contains("n2 -> n1 [label=left];"));
+        assert!(dot.contains("n2 -> n3 [label=right];"));
+        assert!(dot.contains("[label=\"3: C\\\"\", fillcolor=black]"));
+    }
+
+    #[test]
+    fn read_glob() {
+        let ole = open(&build(vec![
+            stream("WordDocument", pattern(6144, 7)),
+            storage("Macros", vec![
+                storage("VBA", vec![stream("dir", pattern(30, 1)), stream("Module1", pattern(5000, 2))]),
+                stream("PROJECT", pattern(40, 3)),
+            ]),
+        ]));
+        let found = ole.read_glob("*/VBA/*").unwrap();
+        assert_eq!(found, vec![
+            ("/Macros/VBA/dir".to_string(), pattern(30, 1)),
+            ("/Macros/VBA/Module1".to_string(), pattern(5000, 2)),
+        ]);
+        let found = ole.read_glob("/Macros/PR?J?CT").unwrap();
+        assert_eq!(found.into_iter().map(|(path, _)| path).collect::<Vec<String>>(), vec!["/Macros/PROJECT"]);
+        assert!(ole.read_glob("*.bin").unwrap().is_empty());
+
+        // a match whose chain ends early fails rather than coming back short
+        let mut ole = ole;
+        assert_eq!(ole.entries.as_ref().unwrap()[6].name(), "Module1");
+        ole.entries.as_mut().unwrap()[6].chain.as_mut().unwrap().truncate(3);
+        assert!(ole.read_glob("*/VBA/*").is_err());
+        assert_eq!(ole.read_glob("*/VBA/dir").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn read_all_streams_and_sanitized_paths() {
+        let ole = open(&build(vec![
+            stream("\u{5}Doc", pattern(10, 1)),
+            stream("\u{1}Doc", pattern(10, 2)),
+            stream("_Doc", pattern(10, 3)),
+            storage("A:B", vec![stream("\u{5}Doc", pattern(10, 4))]),
+            storage("A_B", vec![stream("\u{5}Doc", pattern(10, 5))]),
+        ]));
+        let streams = ole.read_all_streams().unwrap();
+        assert_eq!(streams.len(), 5);
+        assert_eq!(streams["/A:B/\u{5}Doc"], pattern(10, 4));
+        assert_eq!(streams["/A_B/\u{5}Doc"], pattern(10, 5));
+
+        let paths = ole.sanitized_paths().unwrap();
+        assert_eq!(paths.len(), 5);
+        let mut names = paths.values().cloned().collect::<Vec<String>>();
+        names.sort();
+        names.dedup();
+        assert_eq!(names.len(), 5);
+        assert!(names.iter().all(|v| !v.chars().any(|c| c.is_control() || c == ':')));
+        assert_eq!(paths["/A:B/\u{5}Doc"].rsplit_once('/').unwrap().1, "_Doc");
+        assert_ne!(paths["/A:B/\u{5}Doc"], paths["/A_B/\u{5}Doc"]);
+        assert_eq!(ole.sanitized_paths().unwrap(), paths);
+
+        // a stream whose chain ends early fails the whole read rather than coming back short
+        let mut ole = ole;
+        ole.entries.as_mut().unwrap()[1].chain.as_mut().unwrap().clear();
+        assert!(ole.read_all_streams().is_err());
+    }
+
+    #[test]
+    fn walk_survives_degenerate_sibling_chain() {
+        let mut ole = open(&build(vec![]));
+        let entries = ole.entries.as_mut().unwrap();
+        entries.truncate(1);
+        entries[0].child_id = SectorType::RegularSect(1);
+        for i in 1..=200_000u32 {
+            let mut entry = Entry::new(&format!("S{}", i), ObjectType::Stream);
+            entry.right_sibling_id = SectorType::RegularSect(i + 1);
+            entries.push(entry);
+        }
+        // close the chain back on itself, the visited set has to stop the walk
+        entries.last_mut().unwrap().right_sibling_id = SectorType::RegularSect(1);
+
+        let paths = ole.walk().unwrap();
+        assert_eq!(paths.len(), 200_001);
+        assert_eq!(paths[1].0, "/S1");
+        assert_eq!(paths[200_000].0, "/S200000");
+    }
+}
@@ -1,5 +1,512 @@
-
+use crate::common::{OleError, OleResult};
+use crate::directory::{clsid, filetime_systemtime};
+use crate::ole::Ole;
+use std::collections::BTreeMap;
+use std::time::{Duration, SystemTime};
 
 pub struct UserDefinedData {
 
-}
\ No newline at end of file
+}
+
+/// FMTID of the SummaryInformation property set, `{F29F85E0-4FF9-1068-AB91-08002B27B3D9}`
+pub const FMTID_SUMMARY_INFORMATION: [u8; 16] = clsid(0xF29F85E0, 0x4FF9, 0x1068, [0xAB, 0x91, 0x08, 0x00, 0x2B, 0x27, 0xB3, 0xD9]);
+/// FMTID of the DocumentSummaryInformation property set, `{D5CDD502-2E9C-101B-9397-08002B2CF9AE}`
+pub const FMTID_DOC_SUMMARY_INFORMATION: [u8; 16] = clsid(0xD5CDD502, 0x2E9C, 0x101B, [0x93, 0x97, 0x08, 0x00, 0x2B, 0x2C, 0xF9, 0xAE]);
+/// FMTID of the user-defined properties, the second section of DocumentSummaryInformation
+pub const FMTID_USER_DEFINED_PROPERTIES: [u8; 16] = clsid(0xD5CDD505, 0x2E9C, 0x101B, [0x93, 0x97, 0x08, 0x00, 0x2B, 0x2C, 0xF9, 0xAE]);
+
+const VT_I2: u16 = 0x0002;
+const VT_I4: u16 = 0x0003;
+const VT_BOOL: u16 = 0x000B;
+const VT_LPSTR: u16 = 0x001E;
+const VT_LPWSTR: u16 = 0x001F;
+const VT_FILETIME: u16 = 0x0040;
+const VT_BLOB: u16 = 0x0041;
+
+/// property ID of the dictionary mapping property IDs to names
+const PID_DICTIONARY: u32 = 0x0000;
+/// property ID holding the codepage of the section's `VT_LPSTR` strings
+const PID_CODEPAGE: u32 = 0x0001;
+
+/// a decoded property value, strings already converted from the section's codepage
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PropertyValue {
+    I2(i16),
+    I4(i32),
+    Bool(bool),
+    Lpstr(String),
+    Lpwstr(String),
+    /// a raw FILETIME, a point in time or, for some properties, a duration
+    FileTime(u64),
+    Blob(Vec<u8>),
+}
+
+impl PropertyValue {
+    /// the value of either string type
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            PropertyValue::Lpstr(v) | PropertyValue::Lpwstr(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    /// the value of either integer type
+    pub fn as_i32(&self) -> Option<i32> {
+        match self {
+            PropertyValue::I2(v) => Some(*v as i32),
+            PropertyValue::I4(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    pub fn as_filetime(&self) -> Option<u64> {
+        match self {
+            PropertyValue::FileTime(v) => Some(*v),
+            _ => None,
+        }
+    }
+}
+
+/// one section of a property set
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Section {
+    pub fmtid: [u8; 16],
+    /// the codepage property, which `VT_LPSTR` strings were decoded with
+    pub codepage: Option<u16>,
+    /// properties by ID, those of types not decoded here are left out
+    pub properties: BTreeMap<u32, PropertyValue>,
+    /// property names by ID from the dictionary property, empty without one
+    pub dictionary: BTreeMap<u32, String>,
+}
+
+impl Section {
+    pub fn get(&self, id: u32) -> Option<&PropertyValue> {
+        self.properties.get(&id)
+    }
+
+    /// a property by its dictionary name, compared case-insensitively
+    pub fn by_name(&self, name: &str) -> Option<&PropertyValue> {
+        let (id, _) = self.dictionary.iter().find(|(_, v)| v.eq_ignore_ascii_case(name))?;
+        self.properties.get(id)
+    }
+
+    /// the named properties, dictionary names joined with their values
+    pub fn named(&self) -> BTreeMap<String, PropertyValue> {
+        self.dictionary.iter()
+            .filter_map(|(id, name)| Some((name.clone(), self.properties.get(id)?.clone())))
+            .collect()
+    }
+
+    fn string(&self, id: u32) -> Option<String> {
+        self.get(id).and_then(|v| v.as_str()).map(|v| v.to_string())
+    }
+
+    fn int(&self, id: u32) -> Option<i32> {
+        self.get(id).and_then(|v| v.as_i32())
+    }
+
+    fn filetime(&self, id: u32) -> Option<u64> {
+        self.get(id).and_then(|v| v.as_filetime())
+    }
+}
+
+/// a decoded PropertySetStream (MS-OLEPS 2.21) with all of its sections
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PropertySet {
+    pub clsid: [u8; 16],
+    pub sections: Vec<Section>,
+}
+
+impl PropertySet {
+    /// decode a whole property set stream
+    pub fn parse(data: &[u8]) -> OleResult<Self> {
+        if u16_at(data, 0)? != 0xFFFE {
+            return Err(OleError::InvalidFileFormat);
+        }
+        let clsid = bytes_16(data, 8)?;
+        let mut sections = vec![];
+        for i in 0..u32_at(data, 24)? as usize {
+            let at = 28 + i * 20;
+            sections.push(parse_section(data, bytes_16(data, at)?, u32_at(data, at + 16)? as usize)?);
+        }
+        Ok(Self { clsid, sections })
+    }
+
+    /// the section with the given FMTID
+    pub fn section(&self, fmtid: &[u8; 16]) -> Option<&Section> {
+        self.sections.iter().find(|v| &v.fmtid == fmtid)
+    }
+}
+
+/// the document metadata of the `\x05SummaryInformation` stream (MS-OLEPS 2.21 and
+/// the PIDSI_* property IDs), every field `None` when the property isn't there
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SummaryInfo {
+    pub codepage: Option<u16>,
+    pub title: Option<String>,
+    pub subject: Option<String>,
+    pub author: Option<String>,
+    pub keywords: Option<String>,
+    pub comments: Option<String>,
+    pub template: Option<String>,
+    pub last_saved_by: Option<String>,
+    pub revision_number: Option<String>,
+    /// total editing time, stored as a FILETIME-sized tick count
+    pub edit_time: Option<Duration>,
+    pub last_printed: Option<SystemTime>,
+    pub created: Option<SystemTime>,
+    pub last_saved: Option<SystemTime>,
+    pub page_count: Option<i32>,
+    pub word_count: Option<i32>,
+    pub char_count: Option<i32>,
+    pub application_name: Option<String>,
+    pub security: Option<i32>,
+}
+
+impl SummaryInfo {
+    /// decode a whole SummaryInformation stream
+    pub fn parse(data: &[u8]) -> OleResult<Self> {
+        let set = PropertySet::parse(data)?;
+        let section = set.section(&FMTID_SUMMARY_INFORMATION).ok_or(OleError::InvalidFileFormat)?;
+        Ok(Self {
+            codepage: section.codepage,
+            title: section.string(0x02),
+            subject: section.string(0x03),
+            author: section.string(0x04),
+            keywords: section.string(0x05),
+            comments: section.string(0x06),
+            template: section.string(0x07),
+            last_saved_by: section.string(0x08),
+            revision_number: section.string(0x09),
+            edit_time: section.filetime(0x0A).map(|v| Duration::new(v / 10_000_000, (v % 10_000_000) as u32 * 100)),
+            last_printed: section.filetime(0x0B).and_then(filetime_systemtime),
+            created: section.filetime(0x0C).and_then(filetime_systemtime),
+            last_saved: section.filetime(0x0D).and_then(filetime_systemtime),
+            page_count: section.int(0x0E),
+            word_count: section.int(0x0F),
+            char_count: section.int(0x10),
+            application_name: section.string(0x12),
+            security: section.int(0x13),
+        })
+    }
+}
+
+/// the `\x05DocumentSummaryInformation` stream (the PIDDSI_* property IDs) with the
+/// user-defined properties of its second section
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DocumentSummaryInfo {
+    pub codepage: Option<u16>,
+    pub category: Option<String>,
+    pub presentation_format: Option<String>,
+    pub byte_count: Option<i32>,
+    pub line_count: Option<i32>,
+    pub paragraph_count: Option<i32>,
+    pub slide_count: Option<i32>,
+    pub note_count: Option<i32>,
+    pub hidden_count: Option<i32>,
+    pub manager: Option<String>,
+    pub company: Option<String>,
+    /// custom properties by name, as set in the application's properties dialog
+    pub custom: BTreeMap<String, PropertyValue>,
+}
+
+impl DocumentSummaryInfo {
+    /// decode a whole DocumentSummaryInformation stream
+    pub fn parse(data: &[u8]) -> OleResult<Self> {
+        let set = PropertySet::parse(data)?;
+        let section = set.section(&FMTID_DOC_SUMMARY_INFORMATION).ok_or(OleError::InvalidFileFormat)?;
+        Ok(Self {
+            codepage: section.codepage,
+            category: section.string(0x02),
+            presentation_format: section.string(0x03),
+            byte_count: section.int(0x04),
+            line_count: section.int(0x05),
+            paragraph_count: section.int(0x06),
+            slide_count: section.int(0x07),
+            note_count: section.int(0x08),
+            hidden_count: section.int(0x09),
+            manager: section.string(0x0E),
+            company: section.string(0x0F),
+            custom: set.section(&FMTID_USER_DEFINED_PROPERTIES).map(|v| v.named()).unwrap_or_default(),
+        })
+    }
+}
+
+impl Ole {
+    /// decode any property set stream, given by path
+    pub fn property_set(&self, stream_name: &str) -> OleResult<PropertySet> {
+        PropertySet::parse(&self.read_stream(stream_name)?)
+    }
+
+    /// title, author, timestamps and the rest of the document metadata from the
+    /// `\x05SummaryInformation` stream at the root, `None` when there is none
+    pub fn summary_information(&self) -> OleResult<Option<SummaryInfo>> {
+        self.read_root_stream("\u{5}SummaryInformation")?.map(|v| SummaryInfo::parse(&v)).transpose()
+    }
+
+    /// company, manager, counts and custom properties from the
+    /// `\x05DocumentSummaryInformation` stream at the root, `None` when there is none
+    pub fn document_summary_information(&self) -> OleResult<Option<DocumentSummaryInfo>> {
+        self.read_root_stream("\u{5}DocumentSummaryInformation")?.map(|v| DocumentSummaryInfo::parse(&v)).transpose()
+    }
+
+    /// a stream at the root read in full, `None` when there isn't one by that name
+    fn read_root_stream(&self, name: &str) -> OleResult<Option<Vec<u8>>> {
+        match self.root_stream(name) {
+            Ok(entry) => self.read(entry).map(Some),
+            Err(OleError::PathNotFound(_) | OleError::NotAStream(_)) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+}
+
+fn u16_at(data: &[u8], offset: usize) -> OleResult<u16> {
+    let bytes = data.get(offset..offset + 2).ok_or(OleError::InvalidEntrySize)?;
+    Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+fn u32_at(data: &[u8], offset: usize) -> OleResult<u32> {
+    let bytes = data.get(offset..offset + 4).ok_or(OleError::InvalidEntrySize)?;
+    Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+fn bytes_16(data: &[u8], offset: usize) -> OleResult<[u8; 16]> {
+    let bytes = data.get(offset..offset + 16).ok_or(OleError::InvalidEntrySize)?;
+    Ok(bytes.try_into().unwrap())
+}
+
+/// `len` bytes at `offset`
+fn slice_at(data: &[u8], offset: usize, len: usize) -> OleResult<&[u8]> {
+    data.get(offset..offset.saturating_add(len)).ok_or(OleError::InvalidEntrySize)
+}
+
+/// the section at `offset`; the codepage is looked up first since the dictionary
+/// and every `VT_LPSTR` depend on it
+fn parse_section(data: &[u8], fmtid: [u8; 16], offset: usize) -> OleResult<Section> {
+    let section = slice_at(data, offset, u32_at(data, offset)? as usize)?;
+    let mut entries = vec![];
+    for i in 0..u32_at(section, 4)? as usize {
+        entries.push((u32_at(section, 8 + i * 8)?, u32_at(section, 12 + i * 8)? as usize));
+    }
+
+    let mut codepage = None;
+    if let Some((_, at)) = entries.iter().find(|(id, _)| *id == PID_CODEPAGE) {
+        if u16_at(section, *at)? == VT_I2 {
+            codepage = Some(u16_at(section, at + 4)?);
+        }
+    }
+    let unicode = codepage == Some(1200);
+    let lpstr = |bytes: &[u8]| decode_lpstr(bytes, codepage.unwrap_or(1252));
+
+    let mut properties = BTreeMap::new();
+    let mut dictionary = BTreeMap::new();
+    for (id, at) in entries {
+        if id == PID_DICTIONARY {
+            // untyped: a count, then (id, length, name) records, the names in the
+            // section's codepage and padded to 4 bytes when that's UTF-16
+            let mut pos = at + 4;
+            for _ in 0..u32_at(section, at)? {
+                let key = u32_at(section, pos)?;
+                let len = u32_at(section, pos + 4)? as usize;
+                let (bytes, padded) = if unicode { (len * 2, (len * 2).div_ceil(4) * 4) } else { (len, len) };
+                dictionary.insert(key, lpstr(slice_at(section, pos + 8, bytes)?));
+                pos += 8 + padded;
+            }
+            continue;
+        }
+        let value = match u16_at(section, at)? {
+            VT_I2 => PropertyValue::I2(u16_at(section, at + 4)? as i16),
+            VT_I4 => PropertyValue::I4(u32_at(section, at + 4)? as i32),
+            VT_BOOL => PropertyValue::Bool(u16_at(section, at + 4)? != 0),
+            VT_LPSTR => PropertyValue::Lpstr(lpstr(slice_at(section, at + 8, u32_at(section, at + 4)? as usize)?)),
+            VT_LPWSTR => {
+                let chars = u32_at(section, at + 4)? as usize;
+                PropertyValue::Lpwstr(decode_lpstr(slice_at(section, at + 8, chars.saturating_mul(2))?, 1200))
+            }
+            VT_FILETIME => PropertyValue::FileTime(u32_at(section, at + 4)? as u64 | (u32_at(section, at + 8)? as u64) << 32),
+            VT_BLOB => PropertyValue::Blob(slice_at(section, at + 8, u32_at(section, at + 4)? as usize)?.to_vec()),
+            _ => continue,
+        };
+        properties.insert(id, value);
+    }
+    Ok(Section { fmtid, codepage, properties, dictionary })
+}
+
+/// what Windows-1252 puts at 0x80..=0x9F, where it differs from Latin-1
+const CP1252_HIGH: [u16; 32] = [
+    0x20AC, 0x0081, 0x201A, 0x0192, 0x201E, 0x2026, 0x2020, 0x2021, 0x02C6, 0x2030, 0x0160, 0x2039, 0x0152, 0x008D, 0x017D, 0x008F,
+    0x0090, 0x2018, 0x2019, 0x201C, 0x201D, 0x2022, 0x2013, 0x2014, 0x02DC, 0x2122, 0x0161, 0x203A, 0x0153, 0x009D, 0x017E, 0x0178,
+];
+
+/// decode a `VT_LPSTR` up to its terminating NUL: UTF-16 (1200), UTF-8 (65001) and
+/// Windows-1252 are decoded as such, any other codepage as Latin-1, which is right
+/// for its ASCII part at least
+fn decode_lpstr(bytes: &[u8], codepage: u16) -> String {
+    if codepage == 1200 {
+        let units = bytes.chunks_exact(2).map(|v| u16::from_le_bytes([v[0], v[1]])).take_while(|v| *v != 0);
+        return char::decode_utf16(units).map(|v| v.unwrap_or(char::REPLACEMENT_CHARACTER)).collect();
+    }
+    let bytes = &bytes[..bytes.iter().position(|v| *v == 0).unwrap_or(bytes.len())];
+    match codepage {
+        65001 => String::from_utf8_lossy(bytes).into_owned(),
+        1252 => bytes.iter().map(|v| match v {
+            0x80..=0x9F => char::from_u32(CP1252_HIGH[*v as usize - 0x80] as u32).unwrap_or(char::REPLACEMENT_CHARACTER),
+            _ => *v as char,
+        }).collect(),
+        _ => bytes.iter().map(|v| *v as char).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::*;
+
+    fn typed(vt: u16, value: &[u8]) -> Vec<u8> {
+        let mut out = vt.to_le_bytes().to_vec();
+        out.extend([0, 0]);
+        out.extend(value);
+        out.resize(out.len().div_ceil(4) * 4, 0);
+        out
+    }
+
+    fn lpstr(value: &[u8]) -> Vec<u8> {
+        let mut data = ((value.len() + 1) as u32).to_le_bytes().to_vec();
+        data.extend(value);
+        data.push(0);
+        typed(VT_LPSTR, &data)
+    }
+
+    fn utf16(value: &str) -> Vec<u8> {
+        value.encode_utf16().chain([0]).flat_map(|v| v.to_le_bytes()).collect()
+    }
+
+    /// a UTF-16 dictionary, the untyped value of property 0
+    fn dictionary(names: &[(u32, &str)]) -> Vec<u8> {
+        let mut out = (names.len() as u32).to_le_bytes().to_vec();
+        for (id, name) in names {
+            let name = utf16(name);
+            out.extend(id.to_le_bytes());
+            out.extend(((name.len() / 2) as u32).to_le_bytes());
+            out.extend(&name);
+            out.resize(out.len().div_ceil(4) * 4, 0);
+        }
+        out
+    }
+
+    /// property IDs with their serialized values
+    type Properties = Vec<(u32, Vec<u8>)>;
+
+    /// a PropertySetStream with the given sections
+    fn property_stream(sections: Vec<([u8; 16], Properties)>) -> Vec<u8> {
+        let mut out = vec![0xFE, 0xFF, 0, 0, 0x05, 0x01, 0x02, 0x00];
+        out.extend([0; 16]);
+        out.extend((sections.len() as u32).to_le_bytes());
+        let mut offset = 28 + sections.len() * 20;
+        let mut bodies = vec![];
+        for (fmtid, properties) in sections {
+            let mut section = vec![0; 8 + properties.len() * 8];
+            section[4..8].copy_from_slice(&(properties.len() as u32).to_le_bytes());
+            for (i, (id, value)) in properties.into_iter().enumerate() {
+                let at = section.len() as u32;
+                section[8 + i * 8..12 + i * 8].copy_from_slice(&id.to_le_bytes());
+                section[12 + i * 8..16 + i * 8].copy_from_slice(&at.to_le_bytes());
+                section.extend(value);
+            }
+            let size = section.len() as u32;
+            section[..4].copy_from_slice(&size.to_le_bytes());
+            out.extend(fmtid);
+            out.extend((offset as u32).to_le_bytes());
+            offset += section.len();
+            bodies.push(section);
+        }
+        out.extend(bodies.concat());
+        out
+    }
+
+    #[test]
+    fn summary_information() {
+        // unix time 1234567890
+        let created = 128790414900000000u64;
+        let stream_data = property_stream(vec![(FMTID_SUMMARY_INFORMATION, vec![
+            (PID_CODEPAGE, typed(VT_I2, &1252u16.to_le_bytes())),
+            (0x02, lpstr(b"Quarterly \x93Report\x94")),
+            (0x04, lpstr(b"J. Doe")),
+            (0x08, lpstr(b"A. N. Other")),
+            (0x0A, typed(VT_FILETIME, &(90 * 10_000_000u64).to_le_bytes())),
+            (0x0C, typed(VT_FILETIME, &created.to_le_bytes())),
+            (0x0E, typed(VT_I4, &12i32.to_le_bytes())),
+            (0x42, typed(0x0048, &[0; 16])),
+        ])]);
+        let ole = open(&build(vec![
+            stream("WordDocument", pattern(6144, 7)),
+            stream("\u{5}SummaryInformation", stream_data.clone()),
+        ]));
+        let info = ole.summary_information().unwrap().unwrap();
+        assert_eq!(info, SummaryInfo {
+            codepage: Some(1252),
+            title: Some("Quarterly \u{201C}Report\u{201D}".to_string()),
+            author: Some("J. Doe".to_string()),
+            last_saved_by: Some("A. N. Other".to_string()),
+            edit_time: Some(Duration::from_secs(90)),
+            created: Some(SystemTime::UNIX_EPOCH + Duration::from_secs(1234567890)),
+            page_count: Some(12),
+            ..SummaryInfo::default()
+        });
+
+        assert_eq!(open(&build(vec![stream("WordDocument", pattern(6144, 7))])).summary_information().unwrap(), None);
+        assert!(SummaryInfo::parse(&stream_data[..60]).is_err());
+        assert!(SummaryInfo::parse(&property_stream(vec![([1; 16], vec![])])).is_err());
+    }
+
+    #[test]
+    fn document_summary_information() {
+        let stream_data = property_stream(vec![
+            (FMTID_DOC_SUMMARY_INFORMATION, vec![
+                (PID_CODEPAGE, typed(VT_I2, &1200u16.to_le_bytes())),
+                (0x0F, typed(VT_LPSTR, &[&14u32.to_le_bytes()[..], &utf16("Contoso")].concat())),
+                (0x0E, typed(VT_LPSTR, &[&6u32.to_le_bytes()[..], &utf16("Ann")].concat())),
+                (0x06, typed(VT_I4, &3i32.to_le_bytes())),
+            ]),
+            (FMTID_USER_DEFINED_PROPERTIES, vec![
+                (PID_CODEPAGE, typed(VT_I2, &1200u16.to_le_bytes())),
+                (PID_DICTIONARY, dictionary(&[(2, "Client"), (3, "Approved"), (4, "Due"), (5, "Thumb"), (6, "Score")])),
+                (2, typed(VT_LPWSTR, &[&8u32.to_le_bytes()[..], &utf16("Fabrikam")[..16]].concat())),
+                (3, typed(VT_BOOL, &0xFFFFu16.to_le_bytes())),
+                (4, typed(VT_FILETIME, &128790414900000000u64.to_le_bytes())),
+                (5, typed(VT_BLOB, &[&3u32.to_le_bytes()[..], &[1, 2, 3]].concat())),
+                (6, typed(VT_I4, &(-7i32).to_le_bytes())),
+            ]),
+        ]);
+        let ole = open(&build(vec![
+            stream("WordDocument", pattern(6144, 7)),
+            stream("\u{5}DocumentSummaryInformation", stream_data),
+        ]));
+        let info = ole.document_summary_information().unwrap().unwrap();
+        assert_eq!(info.company.as_deref(), Some("Contoso"));
+        assert_eq!(info.manager.as_deref(), Some("Ann"));
+        assert_eq!(info.paragraph_count, Some(3));
+        assert_eq!(info.custom.len(), 5);
+        assert_eq!(info.custom["Client"], PropertyValue::Lpwstr("Fabrikam".to_string()));
+        assert_eq!(info.custom["Approved"], PropertyValue::Bool(true));
+        assert_eq!(info.custom["Due"], PropertyValue::FileTime(128790414900000000));
+        assert_eq!(info.custom["Thumb"], PropertyValue::Blob(vec![1, 2, 3]));
+        assert_eq!(info.custom["Score"], PropertyValue::I4(-7));
+
+        let set = ole.property_set("\u{5}DocumentSummaryInformation").unwrap();
+        assert_eq!(set.sections.len(), 2);
+        let custom = set.section(&FMTID_USER_DEFINED_PROPERTIES).unwrap();
+        assert_eq!(custom.dictionary[&6], "Score");
+        assert_eq!(custom.by_name("client").and_then(|v| v.as_str()), Some("Fabrikam"));
+        assert!(ole.property_set("WordDocument").is_err());
+        assert!(ole.property_set("Missing").is_err());
+    }
+
+    #[test]
+    fn decode_lpstr_codepages() {
+        assert_eq!(decode_lpstr(b"caf\xC3\xA9\0junk", 65001), "café");
+        assert_eq!(decode_lpstr(b"caf\xE9\x80\0", 1252), "café€");
+        assert_eq!(decode_lpstr(b"caf\xE9\x80\0", 28591), "café\u{80}");
+        assert_eq!(decode_lpstr(&[0x63, 0, 0xE9, 0, 0, 0, 0x78, 0], 1200), "cé");
+    }
+}
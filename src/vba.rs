@@ -0,0 +1,253 @@
+use crate::common::{OleError, OleResult};
+use crate::directory::ObjectType;
+use crate::ole::Ole;
+
+/// where Office keeps the VBA storage: Word, Excel and PowerPoint's standalone
+/// `vbaProject.bin` respectively
+const PROJECT_STORAGES: [&str; 3] = ["/Macros", "/_VBA_PROJECT_CUR", ""];
+
+/// the raw streams of a VBA project, still compressed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VbaProject {
+    /// path of the `VBA` storage
+    pub path: String,
+    /// the compressed `dir` stream describing the project and its modules
+    pub dir: Vec<u8>,
+    /// the `_VBA_PROJECT` stream, if present
+    pub vba_project: Option<Vec<u8>>,
+    /// the textual `PROJECT` stream next to the `VBA` storage, if present
+    pub project: Option<Vec<u8>>,
+    /// every other stream of the `VBA` storage by name, the module streams
+    pub modules: Vec<(String, Vec<u8>)>,
+}
+
+impl Ole {
+    /// find the VBA project of a macro-enabled document and return its streams
+    /// as stored, `None` when there is no `VBA` storage with a `dir` stream
+    pub fn vba_project(&self) -> OleResult<Option<VbaProject>> {
+        let entries = self.entries_ref()?;
+        for parent in PROJECT_STORAGES {
+            let path = format!("{}/VBA", parent);
+            let Ok(idx) = self.path_index(&path) else { continue };
+            if entries[idx].object_type != ObjectType::Storage {
+                continue;
+            }
+            let Some(dir) = self.find_child(idx, "dir")? else { continue };
+
+            let read = |idx: usize| self.read_contents(&entries[idx]);
+            let stream = |parent: usize, name: &str| -> OleResult<Option<Vec<u8>>> {
+                match self.find_child(parent, name)? {
+                    Some(idx) if entries[idx].object_type == ObjectType::Stream => read(idx).map(Some),
+                    _ => Ok(None),
+                }
+            };
+
+            let prefix = format!("{}/", path);
+            let mut modules = vec![];
+            for (child, i) in self.entry_paths()? {
+                let Some(name) = child.strip_prefix(&prefix) else { continue };
+                if name.contains('/') || entries[i].object_type != ObjectType::Stream {
+                    continue;
+                }
+                if name != "dir" && name != "_VBA_PROJECT" && !name.starts_with("__SRP_") {
+                    modules.push((name.to_string(), read(i)?));
+                }
+            }
+
+            return Ok(Some(VbaProject {
+                path,
+                dir: read(dir)?,
+                vba_project: stream(idx, "_VBA_PROJECT")?,
+                project: stream(self.path_index(parent)?, "PROJECT")?,
+                modules,
+            }));
+        }
+        Ok(None)
+    }
+}
+
+const CHUNK_SIZE: usize = 4096;
+
+/// decompress an MS-OVBA compressed container, the format of the `dir` stream and
+/// of module source (MS-OVBA 2.4.1)
+pub fn decompress(data: &[u8]) -> OleResult<Vec<u8>> {
+    if data.first() != Some(&0x01) {
+        return Err(OleError::InvalidFileFormat);
+    }
+    let mut out = vec![];
+    let mut pos = 1;
+    while pos < data.len() {
+        let header = u16::from_le_bytes([data[pos], *data.get(pos + 1).ok_or(OleError::InvalidEntrySize)?]);
+        if (header >> 12) & 0b111 != 0b011 {
+            return Err(OleError::InvalidFileFormat);
+        }
+        let end = std::cmp::min(pos + (header & 0x0FFF) as usize + 3, data.len());
+        pos += 2;
+        let chunk_start = out.len();
+
+        if header & 0x8000 == 0 {
+            // stored raw, always a full chunk
+            out.extend(data.get(pos..pos + CHUNK_SIZE).ok_or(OleError::InvalidEntrySize)?);
+            pos += CHUNK_SIZE;
+            continue;
+        }
+        while pos < end {
+            let flags = data[pos];
+            pos += 1;
+            for bit in 0..8 {
+                if pos >= end {
+                    break;
+                }
+                if flags & (1 << bit) == 0 {
+                    out.push(data[pos]);
+                    pos += 1;
+                    continue;
+                }
+                let token = u16::from_le_bytes([data[pos], *data.get(pos + 1).ok_or(OleError::InvalidEntrySize)?]);
+                pos += 2;
+                let (offset_bits, length_mask) = token_layout(out.len() - chunk_start);
+                let length = (token & length_mask) as usize + 3;
+                let offset = (token >> (16 - offset_bits)) as usize + 1;
+                if offset > out.len() - chunk_start {
+                    return Err(OleError::InvalidFileFormat);
+                }
+                // byte by byte, the source may overlap what is being written
+                let from = out.len() - offset;
+                for i in 0..length {
+                    out.push(out[from + i]);
+                }
+            }
+        }
+        pos = end;
+    }
+    Ok(out)
+}
+
+/// bits taken by the offset of a copy token and the mask of its length, both
+/// depending on how much of the chunk has been decompressed so far
+fn token_layout(decompressed: usize) -> (u32, u16) {
+    let mut bits = 4;
+    while (1usize << bits) < decompressed {
+        bits += 1;
+    }
+    (bits, 0xFFFF >> bits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::decompress;
+    use crate::test_util::*;
+
+    #[test]
+    fn vba_project() {
+        let ole = open(&build(vec![
+            stream("WordDocument", pattern(6144, 7)),
+            storage("Macros", vec![
+                stream("PROJECT", pattern(40, 3)),
+                storage("VBA", vec![
+                    stream("dir", pattern(300, 1)),
+                    stream("_VBA_PROJECT", pattern(7, 2)),
+                    stream("__SRP_0", pattern(50, 4)),
+                    stream("ThisDocument", pattern(5000, 5)),
+                    stream("Module1", pattern(900, 6)),
+                ]),
+            ]),
+        ]));
+        let project = ole.vba_project().unwrap().unwrap();
+        assert_eq!(project.path, "/Macros/VBA");
+        assert_eq!(project.dir, pattern(300, 1));
+        assert_eq!(project.vba_project, Some(pattern(7, 2)));
+        assert_eq!(project.project, Some(pattern(40, 3)));
+        assert_eq!(project.modules, vec![
+            ("Module1".to_string(), pattern(900, 6)),
+            ("ThisDocument".to_string(), pattern(5000, 5)),
+        ]);
+
+        // a module whose chain ends early fails rather than coming back short
+        let mut ole = ole;
+        assert_eq!(ole.entries.as_ref().unwrap()[8].name(), "ThisDocument");
+        ole.entries.as_mut().unwrap()[8].chain.as_mut().unwrap().truncate(3);
+        assert!(ole.vba_project().is_err());
+
+        let plain = open(&build(vec![stream("WordDocument", pattern(100, 7))]));
+        assert_eq!(plain.vba_project().unwrap(), None);
+    }
+
+    #[test]
+    fn vba_project_excel_layout() {
+        let ole = open(&build(vec![
+            stream("Workbook", pattern(6144, 7)),
+            storage("_VBA_PROJECT_CUR", vec![
+                stream("PROJECT", pattern(40, 3)),
+                storage("VBA", vec![
+                    stream("dir", pattern(300, 1)),
+                    stream("_VBA_PROJECT", pattern(7, 2)),
+                    stream("Sheet1", pattern(600, 5)),
+                ]),
+            ]),
+        ]));
+        let project = ole.vba_project().unwrap().unwrap();
+        assert_eq!(project.path, "/_VBA_PROJECT_CUR/VBA");
+        assert_eq!(project.dir, pattern(300, 1));
+        assert_eq!(project.vba_project, Some(pattern(7, 2)));
+        assert_eq!(project.project, Some(pattern(40, 3)));
+        assert_eq!(project.modules, vec![("Sheet1".to_string(), pattern(600, 5))]);
+    }
+
+    #[test]
+    fn vba_project_at_root() {
+        // a `VBA` storage without `dir` is skipped for the next candidate
+        let ole = open(&build(vec![
+            storage("Macros", vec![storage("VBA", vec![stream("Module1", pattern(10, 1))])]),
+            stream("PROJECT", pattern(40, 3)),
+            storage("VBA", vec![
+                stream("dir", pattern(300, 1)),
+                stream("Module1", pattern(900, 6)),
+            ]),
+        ]));
+        let project = ole.vba_project().unwrap().unwrap();
+        assert_eq!(project.path, "/VBA");
+        assert_eq!(project.dir, pattern(300, 1));
+        assert_eq!(project.vba_project, None);
+        assert_eq!(project.project, Some(pattern(40, 3)));
+        assert_eq!(project.modules, vec![("Module1".to_string(), pattern(900, 6))]);
+    }
+
+    #[test]
+    fn decompress_spec_examples() {
+        // MS-OVBA 3.2.1, no compression
+        let compressed = [
+            0x01, 0x19, 0xB0, 0x00, 0x61, 0x62, 0x63, 0x64, 0x65, 0x66, 0x67, 0x68, 0x00, 0x69, 0x6A, 0x6B,
+            0x6C, 0x6D, 0x6E, 0x6F, 0x70, 0x00, 0x71, 0x72, 0x73, 0x74, 0x75, 0x76, 0x2E,
+        ];
+        assert_eq!(decompress(&compressed).unwrap(), b"abcdefghijklmnopqrstuv.");
+
+        // MS-OVBA 3.2.2, normal compression
+        let compressed = [
+            0x01, 0x2F, 0xB0, 0x00, 0x23, 0x61, 0x61, 0x61, 0x62, 0x63, 0x64, 0x65, 0x82, 0x66, 0x00, 0x70,
+            0x61, 0x67, 0x68, 0x69, 0x6A, 0x01, 0x38, 0x08, 0x61, 0x6B, 0x6C, 0x00, 0x30, 0x6D, 0x6E, 0x6F,
+            0x70, 0x06, 0x71, 0x02, 0x70, 0x04, 0x10, 0x72, 0x73, 0x74, 0x75, 0x76, 0x10, 0x77, 0x78, 0x79,
+            0x7A, 0x00, 0x3C,
+        ];
+        assert_eq!(decompress(&compressed).unwrap(), b"#aaabcdefaaaaghijaaaaaklaaamnopqaaaaaaaaaaaarstuvwxyzaaa");
+
+        // MS-OVBA 3.2.3, maximum compression
+        assert_eq!(decompress(&[0x01, 0x03, 0xB0, 0x02, 0x61, 0x45, 0x00]).unwrap(), vec![b'a'; 73]);
+
+        assert!(decompress(&[0x02, 0x03, 0xB0]).is_err());
+    }
+
+    #[test]
+    fn decompress_raw_chunk() {
+        // a chunk stored uncompressed is always a full 4096 bytes, after it the
+        // next chunk header follows
+        let mut compressed = vec![0x01, 0xFF, 0x3F];
+        compressed.extend(pattern(4096, 3));
+        compressed.extend([0x02, 0xB0, 0x00, 0x61, 0x62]);
+        let mut expected = pattern(4096, 3);
+        expected.extend(b"ab");
+        assert_eq!(decompress(&compressed).unwrap(), expected);
+
+        assert!(decompress(&compressed[..100]).is_err());
+    }
+}
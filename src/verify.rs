@@ -0,0 +1,362 @@
+use crate::common::{MajorVersion, OleResult, SectorType};
+use crate::directory::{ObjectType, ILLEGAL_NAME_CHARS};
+use crate::ole::{Ole, SPEC_MINI_STREAM_CUTOFF};
+use std::fmt::{Display, Formatter};
+
+/// structural problems found by `Ole::verify`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidationWarning {
+    /// a header count disagrees with what the file actually contains
+    HeaderCountMismatch { field: &'static str, declared: u32, actual: usize },
+    /// a FAT or mini FAT chain loops back on itself
+    ChainCycle { start: u32 },
+    /// a chain points outside its allocation table
+    ChainOutOfRange { start: u32 },
+    /// some directory sectors couldn't be parsed into entries
+    DirectoryIncomplete { expected: usize, parsed: usize },
+    /// a stream's chain length doesn't match its declared size
+    ChainSizeMismatch { entry: usize, expected: usize, actual: usize },
+    /// an entry name contains one of the characters the spec forbids: `/ \ : !`
+    IllegalNameChar { path: String, ch: char },
+    /// the file doesn't end on a sector boundary, it's truncated or has bytes appended
+    NonAlignedLength { remainder: usize },
+    /// the root entry's mini stream size falls short of the mini sectors in use, or
+    /// runs past the sectors of its container
+    MiniStreamSizeMismatch { declared: u64, in_use: u64, capacity: u64 },
+    /// the header's mini stream cutoff isn't the 4096 bytes the spec requires
+    MiniStreamCutoff { actual: u32 },
+    /// the sector shift doesn't match the major version, 9 for version 3 and 12 for version 4
+    SectorShiftMismatch { expected: u16, actual: u16 },
+    /// an entry's name length is odd or above 64 bytes
+    InvalidNameLength { path: String, actual: u16 },
+}
+
+impl Display for ValidationWarning {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ValidationWarning::HeaderCountMismatch { field, declared, actual } => write!(f, "{} declared {}, found {}", field, declared, actual),
+            ValidationWarning::ChainCycle { start } => write!(f, "chain starting at {} loops", start),
+            ValidationWarning::ChainOutOfRange { start } => write!(f, "chain starting at {} leaves the table", start),
+            ValidationWarning::DirectoryIncomplete { expected, parsed } => write!(f, "directory holds {} entries, parsed {}", expected, parsed),
+            ValidationWarning::ChainSizeMismatch { entry, expected, actual } => write!(f, "entry {} needs {} sectors, chain has {}", entry, expected, actual),
+            ValidationWarning::IllegalNameChar { path, ch } => write!(f, "{} contains illegal character {:?}", path, ch),
+            ValidationWarning::NonAlignedLength { remainder } => write!(f, "file ends {} bytes past a sector boundary", remainder),
+            ValidationWarning::MiniStreamSizeMismatch { declared, in_use, capacity } => {
+                write!(f, "mini stream size is {}, {} bytes in use, container holds {}", declared, in_use, capacity)
+            }
+            ValidationWarning::MiniStreamCutoff { actual } => write!(f, "mini stream cutoff is {}, expected 4096", actual),
+            ValidationWarning::SectorShiftMismatch { expected, actual } => write!(f, "sector shift is {}, expected {}", actual, expected),
+            ValidationWarning::InvalidNameLength { path, actual } => write!(f, "{} name length is {}", path, actual),
+        }
+    }
+}
+
+/// directory size as declared by the header against what was actually found,
+/// see `Ole::directory_sector_diagnostic`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DirectoryDiag {
+    /// `number_of_directory_sectors` from the header, which should be 0 for version 3
+    pub declared_sectors: u32,
+    /// sectors in the directory's FAT chain
+    pub walked_sectors: usize,
+    /// entry slots parsed out of those sectors
+    pub total_entries: usize,
+    /// slots holding a storage, stream or the root
+    pub used_entries: usize,
+}
+
+/// follow a chain through an allocation table without trusting it to terminate
+fn walk(table: &[SectorType], start: &SectorType) -> Result<Vec<u32>, ValidationWarning> {
+    let first = u32::from(start.clone());
+    let mut visited = vec![false; table.len()];
+    let mut result = vec![];
+    let mut cur = start;
+    while let SectorType::RegularSect(v) = cur {
+        let idx = *v as usize;
+        match visited.get_mut(idx) {
+            None => return Err(ValidationWarning::ChainOutOfRange { start: first }),
+            Some(true) => return Err(ValidationWarning::ChainCycle { start: first }),
+            Some(seen) => *seen = true,
+        }
+        result.push(*v);
+        cur = &table[idx];
+    }
+    Ok(result)
+}
+
+/// walk a chain, recording a warning and yielding nothing when it's broken
+fn chain(table: &[SectorType], start: &SectorType, warnings: &mut Vec<ValidationWarning>) -> Vec<u32> {
+    walk(table, start).unwrap_or_else(|warning| {
+        warnings.push(warning);
+        vec![]
+    })
+}
+
+impl Ole {
+    /// run the structural checks over a parsed file and report everything that looks wrong
+    pub fn verify(&self) -> OleResult<Vec<ValidationWarning>> {
+        let mut warnings = vec![];
+        self.check_structure(&mut warnings)?;
+        Ok(warnings)
+    }
+
+    /// the checks behind `verify`, adding to `warnings` as they go
+    fn check_structure(&self, warnings: &mut Vec<ValidationWarning>) -> OleResult<()> {
+        let fat = self.fat_ref()?;
+        let entries = self.entries_ref()?;
+        let sector_size = self.header.sector_size();
+
+        let remainder = self.body.iter().map(|v| v.len()).sum::<usize>() % sector_size;
+        if remainder != 0 {
+            warnings.push(ValidationWarning::NonAlignedLength { remainder });
+        }
+        if self.header.number_of_fat_sectors as usize != self.difat.len() {
+            warnings.push(ValidationWarning::HeaderCountMismatch {
+                field: "number_of_fat_sectors",
+                declared: self.header.number_of_fat_sectors,
+                actual: self.difat.len(),
+            });
+        }
+        match self.difat_chain() {
+            Ok(difat) if difat.len() != self.header.number_of_difat_sectors as usize => {
+                warnings.push(ValidationWarning::HeaderCountMismatch {
+                    field: "number_of_difat_sectors",
+                    declared: self.header.number_of_difat_sectors,
+                    actual: difat.len(),
+                });
+            }
+            Ok(_) => {}
+            Err(_) => warnings.push(ValidationWarning::ChainOutOfRange { start: u32::from(self.header.first_difat_sector_location.clone()) }),
+        }
+        let mini_fat_chain = chain(fat, &self.header.first_mini_fat_sector_location, warnings);
+        if mini_fat_chain.len() != self.header.number_of_mini_fat_sectors as usize {
+            warnings.push(ValidationWarning::HeaderCountMismatch {
+                field: "number_of_mini_fat_sectors",
+                declared: self.header.number_of_mini_fat_sectors,
+                actual: mini_fat_chain.len(),
+            });
+        }
+        let directory = chain(fat, &self.header.first_directory_sector_location, warnings);
+        if self.version == MajorVersion::Version4 && directory.len() != self.header.number_of_directory_sectors as usize {
+            warnings.push(ValidationWarning::HeaderCountMismatch {
+                field: "number_of_directory_sectors",
+                declared: self.header.number_of_directory_sectors,
+                actual: directory.len(),
+            });
+        }
+        let expected = directory.len() * (sector_size / 128);
+        if entries.len() != expected {
+            warnings.push(ValidationWarning::DirectoryIncomplete { expected, parsed: entries.len() });
+        }
+
+        let mini_fat = self.mini_fat_ref().map(Vec::as_slice).unwrap_or_default();
+        for (i, entry) in entries.iter().enumerate() {
+            let (table, unit) = match entry.object_type {
+                ObjectType::Stream if self.in_mini_stream(entry) => (mini_fat, self.mini_sector_size()),
+                ObjectType::Stream | ObjectType::RootStorage => (&fat[..], sector_size),
+                _ => continue,
+            };
+            let sectors = chain(table, &entry.starting_sector_location, warnings);
+            let needed = (entry.effective_size(&self.version) as usize).div_ceil(unit);
+            if sectors.len() != needed {
+                warnings.push(ValidationWarning::ChainSizeMismatch { entry: i, expected: needed, actual: sectors.len() });
+            }
+        }
+
+        if let Some(root) = entries.first() {
+            let declared = root.effective_size(&self.version);
+            let used = mini_fat.iter().rposition(|v| v != &SectorType::FreeSect).map_or(0, |v| v + 1);
+            let in_use = (used * self.mini_sector_size()) as u64;
+            let capacity = (walk(fat, &root.starting_sector_location).map_or(0, |v| v.len()) * sector_size) as u64;
+            if declared < in_use || declared > capacity {
+                warnings.push(ValidationWarning::MiniStreamSizeMismatch { declared, in_use, capacity });
+            }
+        }
+
+        for (path, idx) in self.entry_paths()?.into_iter().skip(1) {
+            if let Some(ch) = entries[idx].name().chars().find(|v| ILLEGAL_NAME_CHARS.contains(v)) {
+                warnings.push(ValidationWarning::IllegalNameChar { path, ch });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// lint the header and directory against the spec's MUSTs on top of the `verify`
+    /// checks, on however much of the file has been parsed; unlike `verify` this never
+    /// fails, and none of what it reports stops the file from being read
+    pub fn validate(&self) -> Vec<ValidationWarning> {
+        let header = &self.header;
+        let mut warnings = vec![];
+
+        if header.mini_stream_cutoff_size != SPEC_MINI_STREAM_CUTOFF {
+            warnings.push(ValidationWarning::MiniStreamCutoff { actual: header.mini_stream_cutoff_size });
+        }
+        let expected = match self.version {
+            MajorVersion::Version3 => 9,
+            MajorVersion::Version4 => 12,
+        };
+        if header.sector_shift != expected {
+            warnings.push(ValidationWarning::SectorShiftMismatch { expected, actual: header.sector_shift });
+        }
+        // `verify` holds version 4 headers to the directory's length
+        if self.version == MajorVersion::Version3 && header.number_of_directory_sectors != 0 {
+            warnings.push(ValidationWarning::HeaderCountMismatch {
+                field: "number_of_directory_sectors",
+                declared: header.number_of_directory_sectors,
+                actual: 0,
+            });
+        }
+        if let (Ok(entries), Ok(paths)) = (self.entries_ref(), self.entry_paths()) {
+            for (path, idx) in paths.into_iter().skip(1) {
+                let actual = entries[idx].name_length;
+                if actual % 2 != 0 || actual > 64 {
+                    warnings.push(ValidationWarning::InvalidNameLength { path, actual });
+                }
+            }
+        }
+
+        // an unparsed file stops here
+        let _ = self.check_structure(&mut warnings);
+        warnings
+    }
+
+    /// the directory's declared and actual extent in one place; for version 4 files a
+    /// `declared_sectors` above `walked_sectors` means the directory got truncated
+    pub fn directory_sector_diagnostic(&self) -> DirectoryDiag {
+        let entries = self.entries_ref().map(Vec::as_slice).unwrap_or_default();
+        DirectoryDiag {
+            declared_sectors: self.header.number_of_directory_sectors,
+            walked_sectors: self.directory_ref().map_or(0, |v| v.len()),
+            total_entries: entries.len(),
+            used_entries: entries.iter().filter(|v| v.object_type != ObjectType::Unknown).count(),
+        }
+    }
+
+    /// yes/no gate over `verify`: parsed and free of structural problems
+    pub fn well_formed(&self) -> bool {
+        matches!(self.verify(), Ok(warnings) if warnings.is_empty())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DirectoryDiag, ValidationWarning};
+    use crate::common::SectorType;
+    use crate::test_util::*;
+
+    #[test]
+    fn well_formed() {
+        let bytes = build(vec![
+            stream("WordDocument", pattern(6144, 7)),
+            storage("Storage", vec![stream("Small", pattern(100, 1))]),
+        ]);
+        let ole = open(&bytes);
+        assert_eq!(ole.verify().unwrap(), vec![]);
+        assert!(ole.well_formed());
+        assert!(!open_unparsed(&bytes).well_formed());
+    }
+
+    #[test]
+    fn well_formed_rejects_size_mismatch() {
+        let mut bytes = build(vec![stream("WordDocument", pattern(6144, 7))]);
+        // WordDocument is entry 1 of the directory in sector 1, its size is at 0x78
+        let offset = sector_offset(1) + 128 + 0x78;
+        bytes[offset..offset + 8].copy_from_slice(&8192u64.to_le_bytes());
+        let ole = open(&bytes);
+        assert!(!ole.well_formed());
+        assert_eq!(ole.verify().unwrap(), vec![ValidationWarning::ChainSizeMismatch { entry: 1, expected: 16, actual: 12 }]);
+    }
+
+    #[test]
+    fn verify_flags_non_aligned_length() {
+        let mut bytes = build(vec![stream("WordDocument", pattern(6144, 7))]);
+        bytes.extend([0xAA; 100]);
+        let ole = open(&bytes);
+        assert_eq!(ole.verify().unwrap(), vec![ValidationWarning::NonAlignedLength { remainder: 100 }]);
+        assert_eq!(ole.read_stream("WordDocument").unwrap(), pattern(6144, 7));
+
+        bytes.extend([0xAA; 412]);
+        assert!(open(&bytes).verify().unwrap().is_empty());
+    }
+
+    #[test]
+    fn verify_flags_mini_stream_size() {
+        let mut bytes = build(vec![
+            stream("WordDocument", pattern(6144, 7)),
+            stream("Small", pattern(300, 1)),
+        ]);
+        // the root entry's size sits at 0x78 of the first directory entry
+        let offset = sector_offset(1) + 0x78;
+        assert!(open(&bytes).verify().unwrap().is_empty());
+
+        bytes[offset..offset + 8].copy_from_slice(&4096u64.to_le_bytes());
+        let warnings = open(&bytes).verify().unwrap();
+        assert!(warnings.contains(&ValidationWarning::MiniStreamSizeMismatch { declared: 4096, in_use: 320, capacity: 512 }));
+
+        bytes[offset..offset + 8].copy_from_slice(&256u64.to_le_bytes());
+        let warnings = open(&bytes).verify().unwrap();
+        assert_eq!(warnings, vec![ValidationWarning::MiniStreamSizeMismatch { declared: 256, in_use: 320, capacity: 512 }]);
+    }
+
+    #[test]
+    fn directory_sector_diagnostic() {
+        let nodes = || (0..40).map(|i| stream(&format!("S{}", i), pattern(10, i as u8))).collect();
+        let mut bytes = build_v4(nodes());
+        let ole = open(&bytes);
+        assert_eq!(ole.directory_sector_diagnostic(), DirectoryDiag {
+            declared_sectors: 2,
+            walked_sectors: 2,
+            total_entries: 64,
+            used_entries: 41,
+        });
+
+        // claim a third directory sector that isn't there
+        bytes[0x28..0x2C].copy_from_slice(&3u32.to_le_bytes());
+        let diag = open(&bytes).directory_sector_diagnostic();
+        assert_eq!((diag.declared_sectors, diag.walked_sectors), (3, 2));
+
+        assert_eq!(open(&build(nodes())).directory_sector_diagnostic().declared_sectors, 0);
+    }
+
+    #[test]
+    fn verify_flags_illegal_name_chars() {
+        let ole = open(&build(vec![
+            storage("Storage", vec![stream("a\\b", pattern(10, 1))]),
+            stream("Fine", pattern(10, 2)),
+        ]));
+        assert_eq!(ole.verify().unwrap(), vec![ValidationWarning::IllegalNameChar { path: "/Storage/a\\b".to_string(), ch: '\\' }]);
+    }
+
+    #[test]
+    fn validate() {
+        let nodes = || vec![
+            stream("WordDocument", pattern(6144, 7)),
+            storage("Storage", vec![stream("a:b", pattern(100, 1))]),
+        ];
+        let ole = open(&build(nodes()));
+        assert_eq!(ole.validate(), ole.verify().unwrap());
+        assert_eq!(ole.validate(), vec![ValidationWarning::IllegalNameChar { path: "/Storage/a:b".to_string(), ch: ':' }]);
+
+        let mut bytes = build_with_cutoff(2048, nodes());
+        bytes[0x28..0x2C].copy_from_slice(&1u32.to_le_bytes());
+        let mut ole = open(&bytes);
+        ole.entries.as_mut().unwrap()[1].name_length = 27;
+        // point WordDocument's last sector back at its first
+        let start = u32::from(ole.entries.as_ref().unwrap()[1].starting_sector_location.clone());
+        ole.fat.as_mut().unwrap()[start as usize + 11] = SectorType::RegularSect(start);
+        assert_eq!(ole.validate(), vec![
+            ValidationWarning::MiniStreamCutoff { actual: 2048 },
+            ValidationWarning::HeaderCountMismatch { field: "number_of_directory_sectors", declared: 1, actual: 0 },
+            ValidationWarning::InvalidNameLength { path: "/WordDocument".to_string(), actual: 27 },
+            ValidationWarning::ChainCycle { start },
+            ValidationWarning::ChainSizeMismatch { entry: 1, expected: 12, actual: 0 },
+            ValidationWarning::IllegalNameChar { path: "/Storage/a:b".to_string(), ch: ':' },
+        ]);
+        assert_eq!(ole.read_stream("/Storage/a:b").unwrap(), pattern(100, 1));
+
+        ole.header.sector_shift = 12;
+        assert!(ole.validate().contains(&ValidationWarning::SectorShiftMismatch { expected: 9, actual: 12 }));
+        assert!(open_unparsed(&build(nodes())).validate().is_empty());
+    }
+}
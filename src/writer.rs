@@ -0,0 +1,134 @@
+use crate::common::{MajorVersion, OleResult, SectorType};
+use crate::directory::{Entry, ObjectType};
+use crate::header::Header;
+use crate::ole::{Ole, ParseOptions, SPEC_MINI_STREAM_CUTOFF};
+
+/// builds a version 3 compound file from scratch
+///
+/// streams and storages go in through the editing code, so allocation, the mini
+/// stream cutoff, FAT growth and the balanced red-black sibling trees work exactly
+/// as when editing a parsed file
+pub struct OleWriter {
+    ole: Ole,
+}
+
+impl OleWriter {
+    /// an empty file holding only the root storage
+    pub fn new() -> OleResult<Self> {
+        let header = Header {
+            header_clsid: [0; 16],
+            minor_version: 0x003E,
+            major_version: MajorVersion::Version3,
+            sector_shift: 9,
+            mini_sector_shift: 6,
+            reserved: [0; 6],
+            number_of_directory_sectors: 0,
+            number_of_fat_sectors: 0,
+            first_directory_sector_location: SectorType::EndOfChain,
+            transaction_signature_number: 0,
+            mini_stream_cutoff_size: SPEC_MINI_STREAM_CUTOFF,
+            first_mini_fat_sector_location: SectorType::EndOfChain,
+            number_of_mini_fat_sectors: 0,
+            first_difat_sector_location: SectorType::EndOfChain,
+            number_of_difat_sectors: 0,
+        };
+        let mut ole = Ole {
+            header,
+            version: MajorVersion::Version3,
+            difat: vec![],
+            directory: Some(vec![]),
+            mini_fat: None,
+            fat: Some(vec![]),
+            entries: Some(vec![]),
+            warnings: vec![],
+            mini_fat_sectors: vec![],
+            options: ParseOptions::default(),
+            body: vec![],
+            directory_dirty: false,
+            deferred: None,
+            free_hint: 0,
+        };
+
+        let mut directory = vec![];
+        ole.append_sector(&mut directory, &[])?;
+        ole.header.first_directory_sector_location = directory[0].clone();
+        ole.directory = Some(directory);
+        let per_sector = ole.header.sector_size() / 128;
+        let entries = ole.entries_mut()?;
+        entries.push(Entry::new("Root Entry", ObjectType::RootStorage));
+        entries.extend((1..per_sector).map(|_| Entry::unused()));
+        ole.flush_tables()?;
+        Ok(Self { ole })
+    }
+
+    /// set the CLSID of the root storage
+    pub fn set_clsid(&mut self, clsid: [u8; 16]) -> OleResult<&mut Self> {
+        self.ole.entries_mut()?[0].clsid = clsid;
+        Ok(self)
+    }
+
+    /// add a stream, its parent storages have to exist already
+    pub fn add_stream(&mut self, path: &str, data: &[u8]) -> OleResult<&mut Self> {
+        self.ole.insert_stream(path, data)?;
+        Ok(self)
+    }
+
+    /// add an empty storage, its parent storages have to exist already
+    pub fn add_storage(&mut self, path: &str) -> OleResult<&mut Self> {
+        self.ole.create_storage(path)?;
+        Ok(self)
+    }
+
+    /// the finished file
+    pub fn finish(self) -> OleResult<Vec<u8>> {
+        self.ole.to_bytes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::OleWriter;
+    use crate::common::SectorType;
+    use crate::directory::{Color, ObjectType};
+    use crate::test_util::*;
+
+    #[test]
+    fn write_and_parse() {
+        let mut writer = OleWriter::new().unwrap();
+        writer.set_clsid([7; 16]).unwrap()
+            .add_stream("WordDocument", &pattern(6144, 7)).unwrap()
+            .add_storage("ObjectPool").unwrap()
+            .add_stream("ObjectPool/Small", &pattern(100, 1)).unwrap();
+        for i in 0..10 {
+            writer.add_stream(&format!("S{}", i), &pattern(10 + i, i as u8)).unwrap();
+        }
+        let ole = open(&writer.finish().unwrap());
+
+        assert_eq!(ole.entries.as_ref().unwrap()[0].clsid, [7; 16]);
+        assert_eq!(ole.read_stream("WordDocument").unwrap(), pattern(6144, 7));
+        assert_eq!(ole.read_stream("/ObjectPool/Small").unwrap(), pattern(100, 1));
+        assert!(ole.in_mini_stream(ole.resolve_path("/ObjectPool/Small").unwrap()));
+        for i in 0..10 {
+            assert_eq!(ole.read_stream(format!("S{}", i).as_str()).unwrap(), pattern(10 + i, i as u8));
+        }
+        assert_eq!(ole.iter_streams().count(), 12);
+        assert!(ole.well_formed());
+
+        let entries = ole.entries.as_ref().unwrap();
+        for entry in entries.iter().filter(|v| v.object_type != ObjectType::Stream) {
+            let root = &entry.child_id;
+            if let SectorType::RegularSect(v) = root {
+                assert_eq!(entries[*v as usize].color, Color::Black);
+            }
+            assert!(black_height(entries, root).is_some());
+        }
+    }
+
+    #[test]
+    fn empty_file() {
+        let ole = open(&OleWriter::new().unwrap().finish().unwrap());
+        assert_eq!(ole.entries.as_ref().unwrap()[0].object_type, ObjectType::RootStorage);
+        assert_eq!(ole.iter_streams().count(), 0);
+        assert!(ole.well_formed());
+    }
+}